@@ -2,7 +2,7 @@ use std::hint::black_box;
 
 use criterion::{Criterion, criterion_group, criterion_main};
 use fishtank::{
-    render::Renderer,
+    render::{Renderer, info::Info},
     sim::{Simulation, seed::add_uniform_points, settings::Settings},
 };
 
@@ -13,9 +13,10 @@ fn render_benches(c: &mut Criterion) {
         add_uniform_points(&mut sim, 10_000, 80., 40.);
 
         let renderer = Renderer::new(80, 40);
+        let info = Info::default();
 
         b.iter(|| {
-            let s = renderer.render(&sim, &settings);
+            let s = renderer.render(&sim, &settings, &info);
             black_box(s);
         })
     });
@@ -0,0 +1,513 @@
+use std::{path::PathBuf, str::FromStr, time::Duration};
+
+use clap::Parser;
+
+use crate::{
+    profile::{DEFAULT_PROFILE, load_profile, profile_path},
+    render::runner::TARGET_FPS,
+    sim::{
+        Simulation,
+        boundary::{Boundaries, Boundary},
+        eos::Eos,
+        integrator::Integrator,
+        pressure::PressureModel,
+        runner::Timestep,
+        seed::SeedPattern,
+        settings::{Settings, choice_name},
+        solver::Solver,
+    },
+};
+
+// domain size used when there is no terminal to measure
+const HEADLESS_WIDTH: f64 = 80.;
+const HEADLESS_HEIGHT: f64 = 48.;
+
+// range of step lengths that turn into a usable `Duration`, in milliseconds
+const MIN_TIMESTEP_MS: f64 = 0.001;
+const MAX_TIMESTEP_MS: f64 = 5000.;
+
+// range of frame rates the renderer can aim for
+const MIN_FPS: f64 = 1.;
+const MAX_FPS: f64 = 1000.;
+
+/// A real-time SPH fluid simulator running in the terminal.
+#[derive(Parser, Debug)]
+#[command(version, about, args_override_self = true)]
+pub struct Cli {
+    /// run without a terminal, writing results to `--out`
+    #[arg(long)]
+    pub headless: bool,
+
+    /// number of simulation steps to run in headless mode
+    #[arg(long, default_value_t = 1000, requires = "headless")]
+    pub steps: usize,
+
+    /// directory that headless results are written to
+    #[arg(long, default_value = "fishtank-out", requires = "headless")]
+    pub out: PathBuf,
+
+    /// width of the domain (defaults to the terminal width)
+    #[arg(long, value_parser = finite_positive)]
+    pub width: Option<f64>,
+
+    /// height of the domain (defaults to twice the terminal height)
+    #[arg(long, value_parser = finite_positive)]
+    pub height: Option<f64>,
+
+    /// initial arrangement of the particles
+    #[arg(long, value_enum, default_value_t)]
+    pub seed_pattern: SeedPattern,
+
+    /// target frames per second for the renderer
+    #[arg(long, default_value_t = TARGET_FPS, value_parser = |s: &str| between(s, MIN_FPS, MAX_FPS))]
+    pub fps: f64,
+
+    /// length of one simulation step in milliseconds
+    #[arg(long, value_parser = |s: &str| between(s, MIN_TIMESTEP_MS, MAX_TIMESTEP_MS))]
+    pub timestep_ms: Option<f64>,
+
+    /// number of substeps each simulation step is split into, each split further if the fluid
+    /// moves too fast for it
+    #[arg(long, value_parser = positive::<usize>)]
+    pub substeps: Option<usize>,
+
+    /// seed for all randomness in the sim, so runs can be reproduced (random if not given)
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// start from a snapshot saved with `d` instead of seeding new particles
+    #[arg(long, conflicts_with_all = ["width", "height", "seed_pattern"])]
+    pub snapshot: Option<PathBuf>,
+
+    /// record every input to a log that can be replayed with `--replay`
+    #[arg(long, conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+
+    /// replay a log saved with `--record`, recreating the recorded run
+    #[arg(long)]
+    pub replay: Option<PathBuf>,
+
+    /// obstacles and flows to put in the tank: funnel, dam, aquarium, waterfall, river, or a TOML
+    /// scene file
+    #[arg(long)]
+    pub scene: Option<String>,
+
+    /// how pressure is worked out from the density of the fluid, also switched with `m`
+    #[arg(long, value_enum)]
+    pub pressure: Option<PressureModel>,
+
+    /// equation of state turning density into pressure, also switched with `e`
+    #[arg(long, value_enum)]
+    pub eos: Option<Eos>,
+
+    /// let pressure pull the fluid together where it's thinner than the target density, also
+    /// toggled with `n`
+    #[arg(long)]
+    pub negative_pressure: bool,
+
+    /// how the fluid is moved forward each step, also switched with `v`
+    #[arg(long, value_enum)]
+    pub solver: Option<Solver>,
+
+    /// how particles are moved by the forces on them, also switched with `g`
+    #[arg(long, value_enum)]
+    pub integrator: Option<Integrator>,
+
+    /// settings profile to load and save, either a name or a path to a TOML file
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    #[command(flatten)]
+    pub params: ParamArgs,
+
+    #[command(flatten)]
+    pub boundaries: BoundaryArgs,
+}
+
+/// Initial values for the simulation parameters.
+#[derive(clap::Args, Debug)]
+#[command(next_help_heading = "Parameters")]
+pub struct ParamArgs {
+    /// number of fluid particles
+    #[arg(long)]
+    pub particles: Option<f64>,
+    /// downward force
+    #[arg(long)]
+    pub gravity: Option<f64>,
+    /// rest density of the fluid
+    #[arg(long)]
+    pub density: Option<f64>,
+    /// fluid thickness/resistance
+    #[arg(long)]
+    pub viscosity: Option<f64>,
+    /// pressure response strength
+    #[arg(long)]
+    pub stiffness: Option<f64>,
+    /// particle interaction distance
+    #[arg(long)]
+    pub smoothing_radius: Option<f64>,
+    /// boundary energy loss
+    #[arg(long)]
+    pub dampening: Option<f64>,
+    /// strength of mouse interactions
+    #[arg(long)]
+    pub mouse_force: Option<f64>,
+    /// range of mouse forces
+    #[arg(long)]
+    pub mouse_radius: Option<f64>,
+    /// simulation speed relative to real time
+    #[arg(long)]
+    pub time_scale: Option<f64>,
+    /// number of fish swimming in the tank
+    #[arg(long)]
+    pub fish: Option<f64>,
+    /// tilt of the tank in degrees, positive pulls the water right
+    #[arg(long, allow_negative_numbers = true)]
+    pub gravity_angle: Option<f64>,
+    /// surface tension pulling the fluid into droplets
+    #[arg(long)]
+    pub cohesion: Option<f64>,
+    /// exponent of the Tait equation of state, higher is stiffer
+    #[arg(long)]
+    pub tait_gamma: Option<f64>,
+    /// moves particles partly along with their neighbors, for orderly flow at low particle counts
+    #[arg(long)]
+    pub xsph: Option<f64>,
+    /// damps particles rushing at each other, calming noisy flow without thickening it
+    #[arg(long)]
+    pub artificial_viscosity: Option<f64>,
+}
+
+/// What happens to particles at the edges of the tank.
+#[derive(clap::Args, Debug)]
+#[command(next_help_heading = "Boundaries")]
+pub struct BoundaryArgs {
+    /// boundary on every edge, unless given for a single edge below
+    #[arg(long, value_enum)]
+    pub boundary: Option<Boundary>,
+    /// boundary on the left edge
+    #[arg(long, value_enum)]
+    pub left: Option<Boundary>,
+    /// boundary on the right edge
+    #[arg(long, value_enum)]
+    pub right: Option<Boundary>,
+    /// boundary on the top edge
+    #[arg(long, value_enum)]
+    pub top: Option<Boundary>,
+    /// boundary on the bottom edge
+    #[arg(long, value_enum)]
+    pub bottom: Option<Boundary>,
+}
+
+impl Cli {
+    /// parses the command line on top of the arguments saved in a recording, so that anything
+    /// given explicitly still takes precedence
+    pub fn parse_with_recorded_args(recorded: &[String]) -> Self {
+        let args = std::iter::once("fishtank".to_string())
+            .chain(recorded.iter().cloned())
+            .chain(std::env::args().skip(1));
+        Self::parse_from(args)
+    }
+
+    /// arguments that recreate the initial state of `sim`, saved at the start of a recording
+    pub fn recorded_args(&self, sim: &Simulation, settings: &Settings) -> Vec<String> {
+        let mut args = vec!["--seed".to_string(), sim.seed().to_string()];
+
+        match &self.snapshot {
+            Some(path) => args.extend(["--snapshot".to_string(), path.display().to_string()]),
+            None => {
+                args.extend([
+                    "--width".to_string(),
+                    sim.width().to_string(),
+                    "--height".to_string(),
+                    sim.height().to_string(),
+                    "--seed-pattern".to_string(),
+                    choice_name(&self.seed_pattern),
+                ]);
+            }
+        }
+
+        if let Some(scene) = &self.scene {
+            args.extend(["--scene".to_string(), scene.clone()]);
+        }
+
+        let timestep = self.timestep();
+        args.extend([
+            "--timestep-ms".to_string(),
+            (timestep.step.as_secs_f64() * 1000.).to_string(),
+            "--substeps".to_string(),
+            timestep.substeps.to_string(),
+        ]);
+
+        // every parameter, so the replay doesn't depend on whatever profile is around
+        for (key, param) in Settings::KEYS.iter().zip(settings.params()) {
+            args.extend([format!("--{}", key), param.value().to_string()]);
+        }
+
+        args.extend([
+            "--pressure".to_string(),
+            choice_name(&settings.pressure_model()),
+            "--eos".to_string(),
+            choice_name(&settings.eos()),
+            "--solver".to_string(),
+            choice_name(&settings.solver()),
+            "--integrator".to_string(),
+            choice_name(&settings.integrator()),
+        ]);
+
+        if settings.negative_pressure() {
+            args.push("--negative-pressure".to_string());
+        }
+
+        let boundaries = settings.boundaries();
+        for (edge, boundary) in [
+            ("--left", boundaries.left),
+            ("--right", boundaries.right),
+            ("--top", boundaries.top),
+            ("--bottom", boundaries.bottom),
+        ] {
+            args.extend([edge.to_string(), choice_name(&boundary)]);
+        }
+
+        args
+    }
+
+    /// the domain size, falling back to `default` (or a fixed size when headless)
+    pub fn domain_size(&self, default: Option<(f64, f64)>) -> (f64, f64) {
+        let (width, height) = default.unwrap_or((HEADLESS_WIDTH, HEADLESS_HEIGHT));
+        (self.width.unwrap_or(width), self.height.unwrap_or(height))
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed.unwrap_or_else(rand::random)
+    }
+
+    pub fn timestep(&self) -> Timestep {
+        let mut timestep = Timestep::default();
+        if let Some(ms) = self.timestep_ms {
+            timestep.step = Duration::from_secs_f64(ms / 1000.);
+        }
+        if let Some(substeps) = self.substeps {
+            timestep.substeps = substeps;
+        }
+        timestep
+    }
+
+    pub fn profile_path(&self) -> anyhow::Result<PathBuf> {
+        profile_path(self.profile.as_deref().unwrap_or(DEFAULT_PROFILE))
+    }
+
+    /// builds the initial settings from the profile (if there is one) and any parameters given
+    /// on the command line, checking each value against its parameter's range
+    pub fn settings(&self) -> anyhow::Result<Settings> {
+        let mut settings = Settings::default();
+
+        // a missing default profile is fine, but one that was asked for must exist. headless
+        // runs leave the default one alone, so they don't depend on whoever's running them
+        let path = self.profile_path()?;
+        if self.profile.is_some() || (!self.headless && path.exists()) {
+            load_profile(&path, &mut settings)?;
+        }
+
+        self.params.apply(&mut settings)?;
+        settings.set_boundaries(self.boundaries.apply(settings.boundaries()))?;
+        if let Some(model) = self.pressure {
+            settings.set_pressure_model(model);
+        }
+        if let Some(eos) = self.eos {
+            settings.set_eos(eos);
+        }
+        if self.negative_pressure {
+            settings.set_negative_pressure(true);
+        }
+        if let Some(solver) = self.solver {
+            settings.set_solver(solver);
+        }
+        if let Some(integrator) = self.integrator {
+            settings.set_integrator(integrator);
+        }
+        Ok(settings)
+    }
+}
+
+impl ParamArgs {
+    pub fn apply(&self, settings: &mut Settings) -> anyhow::Result<()> {
+        // in the same order as `Settings::KEYS`
+        let values = [
+            self.particles,
+            self.gravity,
+            self.density,
+            self.viscosity,
+            self.stiffness,
+            self.smoothing_radius,
+            self.dampening,
+            self.mouse_force,
+            self.mouse_radius,
+            self.time_scale,
+            self.fish,
+            self.gravity_angle,
+            self.cohesion,
+            self.tait_gamma,
+            self.xsph,
+            self.artificial_viscosity,
+        ];
+
+        for (idx, value) in values.into_iter().enumerate() {
+            if let Some(value) = value {
+                settings.set_value(idx, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BoundaryArgs {
+    /// `boundaries` with any edges given on the command line replaced
+    pub fn apply(&self, boundaries: Boundaries) -> Boundaries {
+        let all = self.boundary;
+        Boundaries {
+            left: self.left.or(all).unwrap_or(boundaries.left),
+            right: self.right.or(all).unwrap_or(boundaries.right),
+            top: self.top.or(all).unwrap_or(boundaries.top),
+            bottom: self.bottom.or(all).unwrap_or(boundaries.bottom),
+        }
+    }
+}
+
+fn positive<T>(s: &str) -> Result<T, String>
+where
+    T: FromStr + PartialOrd + Default,
+{
+    let value = s
+        .parse::<T>()
+        .map_err(|_| format!("`{}` is not a number", s))?;
+    if value > T::default() {
+        Ok(value)
+    } else {
+        Err(format!("must be greater than zero, got {}", s))
+    }
+}
+
+fn finite_positive(s: &str) -> Result<f64, String> {
+    let value = positive::<f64>(s)?;
+    if value.is_finite() {
+        Ok(value)
+    } else {
+        Err(format!("must be finite, got {}", s))
+    }
+}
+
+fn between(s: &str, min: f64, max: f64) -> Result<f64, String> {
+    let value = s
+        .parse::<f64>()
+        .map_err(|_| format!("`{}` is not a number", s))?;
+    if (min..=max).contains(&value) {
+        Ok(value)
+    } else {
+        Err(format!("must be between {} and {}, got {}", min, max, s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // headless, so that the settings don't depend on whatever default profile is around
+    fn headless(args: &[&str]) -> Cli {
+        Cli::try_parse_from(["fishtank", "--headless"].iter().chain(args)).unwrap()
+    }
+
+    #[test]
+    fn parse_params() {
+        let cli = headless(&["--gravity", "20", "--smoothing-radius", "3"]);
+        let settings = cli.settings().unwrap();
+
+        assert_eq!(settings.gravity(), 20.);
+        assert_eq!(settings.smoothing_radius(), 3.);
+    }
+
+    #[test]
+    fn reject_out_of_range_param() {
+        let cli = headless(&["--stiffness", "10000"]);
+        let err = cli.settings().err().unwrap();
+
+        assert_eq!(
+            err.to_string(),
+            "stiffness must be between 0 and 9000, got 10000"
+        );
+    }
+
+    #[test]
+    fn recorded_args_recreate_run() {
+        let cli = headless(&["--seed-pattern", "dam", "--gravity", "3"]);
+        let sim = Simulation::with_seed(50., 20., 9);
+        let settings = cli.settings().unwrap();
+
+        let args = cli.recorded_args(&sim, &settings);
+        let replayed =
+            Cli::try_parse_from(std::iter::once("fishtank".to_string()).chain(args)).unwrap();
+
+        assert_eq!(replayed.seed, Some(9));
+        assert_eq!(replayed.domain_size(None), (50., 20.));
+        assert_eq!(replayed.seed_pattern, SeedPattern::Dam);
+        assert_eq!(replayed.params.gravity, Some(3.));
+        assert_eq!(replayed.timestep().step, cli.timestep().step);
+    }
+
+    #[test]
+    fn parse_boundaries() {
+        let cli = headless(&[
+            "--boundary",
+            "periodic",
+            "--top",
+            "open",
+            "--bottom",
+            "sticky",
+        ]);
+        let boundaries = cli.settings().unwrap().boundaries();
+
+        assert_eq!(boundaries.left, Boundary::Periodic);
+        assert_eq!(boundaries.right, Boundary::Periodic);
+        assert_eq!(boundaries.top, Boundary::Open);
+        assert_eq!(boundaries.bottom, Boundary::Sticky);
+
+        let cli = headless(&["--left", "periodic"]);
+        assert!(cli.settings().is_err());
+    }
+
+    #[test]
+    fn parse_negative_param() {
+        let cli = Cli::try_parse_from(["fishtank", "--gravity-angle", "-30"]).unwrap();
+        assert_eq!(cli.params.gravity_angle, Some(-30.));
+    }
+
+    #[test]
+    fn later_args_take_precedence() {
+        let cli = Cli::try_parse_from(["fishtank", "--gravity", "3", "--gravity", "4"]).unwrap();
+        assert_eq!(cli.params.gravity, Some(4.));
+    }
+
+    #[test]
+    fn reject_non_positive_size() {
+        assert!(Cli::try_parse_from(["fishtank", "--width", "0"]).is_err());
+        assert!(Cli::try_parse_from(["fishtank", "--substeps", "0"]).is_err());
+        assert!(Cli::try_parse_from(["fishtank", "--timestep-ms", "inf"]).is_err());
+    }
+
+    #[test]
+    fn reject_unusable_timing() {
+        for (arg, value) in [
+            ("--timestep-ms", "1e30"),
+            ("--timestep-ms", "1e-7"),
+            ("--timestep-ms", "NaN"),
+            ("--fps", "1e-300"),
+            ("--fps", "0"),
+        ] {
+            assert!(Cli::try_parse_from(["fishtank", arg, value]).is_err());
+        }
+        assert_eq!(
+            headless(&["--timestep-ms", "0.001"]).timestep().step,
+            Duration::from_micros(1)
+        );
+    }
+}
@@ -1,28 +1,103 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use crossterm::event::{self, KeyCode, MouseEventKind};
 
 use crate::{
+    profile::save_profile,
     render::{Renderer, info::Info},
-    sim::{Simulation, settings::Settings},
+    sim::{
+        MouseForce, Simulation, body::BodyKind, input::Input, preset::Preset, runner::Playback,
+        settings::Settings,
+    },
 };
 
+/// What clicking and dragging the mouse does.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tool {
+    /// left attracts, right repels
+    Force,
+    /// paints walls
+    Wall,
+    /// erases painted walls
+    Eraser,
+    /// pours in new particles
+    Faucet,
+    /// soaks up particles
+    Sponge,
+}
+
+impl Tool {
+    pub fn name(self) -> &'static str {
+        match self {
+            Tool::Force => "Force",
+            Tool::Wall => "Wall",
+            Tool::Eraser => "Eraser",
+            Tool::Faucet => "Faucet",
+            Tool::Sponge => "Sponge",
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            Tool::Force => Tool::Wall,
+            Tool::Wall => Tool::Eraser,
+            Tool::Eraser => Tool::Faucet,
+            Tool::Faucet => Tool::Sponge,
+            Tool::Sponge => Tool::Force,
+        }
+    }
+}
+
+/// Everything the event loop needs besides the shared state.
+pub struct Controls<'a> {
+    /// where `w` saves the current settings
+    pub profile: &'a Path,
+    /// presets selected with the number keys
+    pub presets: &'a [Preset],
+    /// whether inputs are being recorded, in which case the history can't be scrubbed
+    pub recording: bool,
+}
+
 pub fn run_event_loop(
     sim: Arc<Mutex<Simulation>>,
     settings: Arc<Mutex<Settings>>,
     renderer: Arc<Mutex<Renderer>>,
     info: Arc<Mutex<Info>>,
+    playback: Arc<Mutex<Playback>>,
+    controls: Controls,
 ) -> anyhow::Result<()> {
+    let Controls {
+        profile,
+        presets,
+        recording,
+    } = controls;
+    let mut preset_idx = None;
+    // bodies are dropped above wherever the mouse was last pressed
+    let mut drop_x = None;
+    let mut tool = Tool::Force;
+    // cell the wall tools last painted, so that fast drags don't leave gaps
+    let mut last_cell = None;
+
     loop {
         // blocking wait for events - no need to poll at high rate
         let event = event::read()?;
+        // while a replay is driving the sim, inputs that change it are ignored
+        let replaying = playback.lock().unwrap().replaying();
 
         match event {
             event::Event::Resize(cols, rows) => {
-                let mut renderer = renderer.lock().unwrap();
-                renderer.resize(rows as usize, cols as usize);
-                let mut sim = sim.lock().unwrap();
-                sim.resize(cols as f64, 2. * rows as f64);
+                renderer.lock().unwrap().resize(rows as usize, cols as usize);
+                if !replaying {
+                    let mut sim = sim.lock().unwrap();
+                    sim.push_input(Input::Resize {
+                        width: cols as f64,
+                        height: 2. * rows as f64,
+                    });
+                }
             }
             event::Event::Key(event) => {
                 match event.code {
@@ -38,13 +113,129 @@ pub fn run_event_loop(
                         let mut info = info.lock().unwrap();
                         info.toggle_visibility();
                     }
-                    KeyCode::Char('r') => {
-                        let mut settings = settings.lock().unwrap();
-                        settings.reset_selected();
-                        let target_count = settings.particle_count();
-                        drop(settings);
+                    KeyCode::Char(' ') => {
+                        let mut playback = playback.lock().unwrap();
+                        playback.toggle_pause();
+                    }
+                    KeyCode::Char('.') => {
+                        // advance a single step while paused
+                        let mut playback = playback.lock().unwrap();
+                        playback.request_step();
+                    }
+                    KeyCode::Char(c @ ('-' | '+' | '=')) if !replaying => {
+                        // slow down / speed up
+                        change_settings(&sim, &settings, |settings| {
+                            let idx = Settings::TIME_SCALE_IDX;
+                            if c == '-' {
+                                settings.dec_param(idx);
+                            } else {
+                                settings.inc_param(idx);
+                            }
+                            Some(param_input(settings, idx))
+                        });
+                    }
+                    KeyCode::Char(c @ ('[' | ']')) if !replaying => {
+                        // tilt the tank a little further left or right
+                        change_settings(&sim, &settings, |settings| {
+                            let idx = Settings::GRAVITY_ANGLE_IDX;
+                            if c == '[' {
+                                settings.dec_param(idx);
+                            } else {
+                                settings.inc_param(idx);
+                            }
+                            Some(param_input(settings, idx))
+                        });
+                    }
+                    KeyCode::Char('x') if !replaying => {
+                        sim.lock().unwrap().push_input(Input::Shake);
+                    }
+                    KeyCode::Char('m') if !replaying => {
+                        // switch pressure model, applied right away like any other setting
+                        change_settings(&sim, &settings, |settings| {
+                            let model = settings.pressure_model().next();
+                            settings.set_pressure_model(model);
+                            Some(Input::Pressure(model))
+                        });
+                    }
+                    KeyCode::Char('e') if !replaying => {
+                        change_settings(&sim, &settings, |settings| {
+                            let eos = settings.eos().next();
+                            settings.set_eos(eos);
+                            Some(Input::Eos(eos))
+                        });
+                    }
+                    KeyCode::Char('n') if !replaying => {
+                        change_settings(&sim, &settings, |settings| {
+                            let on = !settings.negative_pressure();
+                            settings.set_negative_pressure(on);
+                            Some(Input::NegativePressure(on))
+                        });
+                    }
+                    KeyCode::Char('v') if !replaying => {
+                        change_settings(&sim, &settings, |settings| {
+                            let solver = settings.solver().next();
+                            settings.set_solver(solver);
+                            Some(Input::Solver(solver))
+                        });
+                    }
+                    KeyCode::Char('g') if !replaying => {
+                        change_settings(&sim, &settings, |settings| {
+                            let integrator = settings.integrator().next();
+                            settings.set_integrator(integrator);
+                            Some(Input::Integrator(integrator))
+                        });
+                    }
+                    KeyCode::Char('w') => {
+                        // write the current tuning out to the active profile
+                        let result = save_profile(profile, &settings.lock().unwrap());
+                        let mut info = info.lock().unwrap();
+                        match result {
+                            Ok(()) => info.set_status("Profile saved"),
+                            Err(_) => info.set_status("Profile not saved!"),
+                        }
+                    }
+                    KeyCode::Char('d') => {
+                        // dump the current tank to a snapshot in the working directory
+                        let path = snapshot_path();
+                        let result = sim.lock().unwrap().snapshot().save(&path);
+                        let mut info = info.lock().unwrap();
+                        match result {
+                            Ok(()) => info.set_status("Snapshot saved"),
+                            Err(_) => info.set_status("Snapshot not saved!"),
+                        }
+                    }
+                    KeyCode::Char('t') => {
+                        tool = tool.next();
+                        info.lock().unwrap().set_tool(tool.name());
+                    }
+                    KeyCode::Char(c @ ('b' | 'c' | 'o')) if !replaying => {
+                        let kind = match c {
+                            'b' => BodyKind::Boat,
+                            'c' => BodyKind::Crate,
+                            _ => BodyKind::Ball,
+                        };
                         let mut sim = sim.lock().unwrap();
-                        sim.sync_particle_count(target_count);
+                        let x = drop_x.unwrap_or(sim.width() / 2.);
+                        sim.push_input(Input::Drop { kind, x, y: 4. });
+                    }
+                    KeyCode::Char(c @ '1'..='9') if !replaying => {
+                        let idx = c as usize - '1' as usize;
+                        if let Some(preset) = presets.get(idx) {
+                            preset_idx = Some(idx);
+                            apply_preset(preset, &sim, &settings, &info);
+                        }
+                    }
+                    KeyCode::Char('p') if !presets.is_empty() && !replaying => {
+                        // cycle through the presets
+                        let idx = preset_idx.map_or(0, |idx| (idx + 1) % presets.len());
+                        preset_idx = Some(idx);
+                        apply_preset(&presets[idx], &sim, &settings, &info);
+                    }
+                    KeyCode::Char('r') if !replaying => {
+                        change_settings(&sim, &settings, |settings| {
+                            settings.reset_selected();
+                            selected_param_input(settings)
+                        });
                     }
                     KeyCode::Down => {
                         let mut settings = settings.lock().unwrap();
@@ -54,43 +245,83 @@ pub fn run_event_loop(
                         let mut settings = settings.lock().unwrap();
                         settings.select_prev();
                     }
-                    KeyCode::Right => {
-                        let mut settings = settings.lock().unwrap();
-                        settings.inc_selected();
-                        let target_count = settings.particle_count();
-                        drop(settings);
-                        let mut sim = sim.lock().unwrap();
-                        sim.sync_particle_count(target_count);
-                    }
-                    KeyCode::Left => {
-                        let mut settings = settings.lock().unwrap();
-                        settings.dec_selected();
-                        let target_count = settings.particle_count();
-                        drop(settings);
-                        let mut sim = sim.lock().unwrap();
-                        sim.sync_particle_count(target_count);
+                    KeyCode::Right | KeyCode::Left if !replaying => {
+                        let forward = event.code == KeyCode::Right;
+
+                        // with the settings panel hidden, the arrows scrub through the history
+                        let visible = settings.lock().unwrap().visible();
+                        if !visible && recording {
+                            info.lock().unwrap().set_status("Can't rewind while recording");
+                        } else if !visible {
+                            let mut playback = playback.lock().unwrap();
+                            playback.scrub(if forward { 1 } else { -1 });
+                        } else {
+                            change_settings(&sim, &settings, |settings| {
+                                if forward {
+                                    settings.inc_selected();
+                                } else {
+                                    settings.dec_selected();
+                                }
+                                selected_param_input(settings)
+                            });
+                        }
                     }
                     _ => {}
                 }
             }
-            event::Event::Mouse(event) => {
+            event::Event::Mouse(event) if !replaying => {
                 let mut sim = sim.lock().unwrap();
                 match event.kind {
                     MouseEventKind::Down(btn) | MouseEventKind::Drag(btn) => {
                         let center = (event.column as f64, event.row as f64 * 2.);
+                        drop_x = Some(center.0);
 
-                        match btn {
-                            event::MouseButton::Left => {
-                                sim.mouse_force.set_positive(center.0, center.1);
+                        match (tool, btn) {
+                            (Tool::Force, event::MouseButton::Left) => {
+                                sim.push_input(Input::Mouse(MouseForce::Positive {
+                                    x: center.0,
+                                    y: center.1,
+                                }));
+                            }
+                            (Tool::Force, event::MouseButton::Right) => {
+                                sim.push_input(Input::Mouse(MouseForce::Negative {
+                                    x: center.0,
+                                    y: center.1,
+                                }));
+                            }
+                            (Tool::Force, event::MouseButton::Middle) => {}
+                            (Tool::Faucet, _) => {
+                                sim.push_input(Input::Mouse(MouseForce::Faucet {
+                                    x: center.0,
+                                    y: center.1,
+                                }));
                             }
-                            event::MouseButton::Right => {
-                                sim.mouse_force.set_negative(center.0, center.1);
+                            (Tool::Sponge, _) => {
+                                sim.push_input(Input::Mouse(MouseForce::Sponge {
+                                    x: center.0,
+                                    y: center.1,
+                                }));
+                            }
+                            (Tool::Wall | Tool::Eraser, _) => {
+                                let cell = (event.column as usize, event.row as usize);
+                                let from = match event.kind {
+                                    MouseEventKind::Drag(_) => last_cell.unwrap_or(cell),
+                                    _ => cell,
+                                };
+                                for (col, row) in cells_between(from, cell) {
+                                    sim.push_input(Input::Wall {
+                                        col,
+                                        row,
+                                        solid: tool == Tool::Wall,
+                                    });
+                                }
+                                last_cell = Some(cell);
                             }
-                            event::MouseButton::Middle => {}
                         }
                     }
                     MouseEventKind::Up(..) => {
-                        sim.mouse_force.reset();
+                        last_cell = None;
+                        sim.push_input(Input::Mouse(MouseForce::None));
                     }
                     _ => {}
                 }
@@ -101,3 +332,90 @@ pub fn run_event_loop(
 
     Ok(())
 }
+
+fn apply_preset(
+    preset: &Preset,
+    sim: &Mutex<Simulation>,
+    settings: &Mutex<Settings>,
+    info: &Mutex<Info>,
+) {
+    change_settings(sim, settings, |settings| {
+        if preset.apply(settings).is_err() {
+            info.lock().unwrap().set_status("Preset not applied!");
+            return Vec::new();
+        }
+        (0..settings.params().len())
+            .map(|idx| param_input(settings, idx))
+            .collect()
+    });
+}
+
+// Settings are changed straight away so the panel updates, but the sim sees the change as an
+// input so that it's applied (and recorded) between steps. The sim stays locked until the input
+// is queued, so that no step can run with the change before it's been recorded.
+fn change_settings<I: IntoIterator<Item = Input>>(
+    sim: &Mutex<Simulation>,
+    settings: &Mutex<Settings>,
+    change: impl FnOnce(&mut Settings) -> I,
+) {
+    // locked in the same order as the sim loop
+    let mut sim = sim.lock().unwrap();
+    let mut settings = settings.lock().unwrap();
+    for input in change(&mut settings) {
+        sim.push_input(input);
+    }
+}
+
+fn selected_param_input(settings: &Settings) -> Option<Input> {
+    settings
+        .visible()
+        .then(|| param_input(settings, settings.selected_idx()))
+}
+
+fn param_input(settings: &Settings, idx: usize) -> Input {
+    Input::Param {
+        idx,
+        value: *settings.params()[idx].value(),
+    }
+}
+
+// every cell on a straight line from `from` to `to`, inclusive
+fn cells_between(from: (usize, usize), to: (usize, usize)) -> Vec<(usize, usize)> {
+    let delta = (to.0 as f64 - from.0 as f64, to.1 as f64 - from.1 as f64);
+    let steps = delta.0.abs().max(delta.1.abs()) as usize;
+
+    (0..=steps)
+        .map(|step| {
+            let t = if steps == 0 {
+                0.
+            } else {
+                step as f64 / steps as f64
+            };
+            (
+                (from.0 as f64 + t * delta.0).round() as usize,
+                (from.1 as f64 + t * delta.1).round() as usize,
+            )
+        })
+        .collect()
+}
+
+fn snapshot_path() -> PathBuf {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    PathBuf::from(format!("fishtank-{}.snap", secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fill_gaps_in_drags() {
+        assert_eq!(cells_between((2, 3), (2, 3)), [(2, 3)]);
+        assert_eq!(
+            cells_between((0, 0), (4, 2)),
+            [(0, 0), (1, 1), (2, 1), (3, 2), (4, 2)]
+        );
+    }
+}
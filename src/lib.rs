@@ -1,3 +1,8 @@
+pub mod cli;
 pub mod event_loop;
+pub mod headless;
+pub mod profile;
+pub mod recording;
 pub mod render;
+pub mod scene;
 pub mod sim;
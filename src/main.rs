@@ -3,6 +3,7 @@ use std::{
     sync::{Arc, Mutex},
 };
 
+use clap::{CommandFactory, Parser, error::ErrorKind};
 use crossterm::{
     cursor::{Hide, Show},
     event::{DisableMouseCapture, EnableMouseCapture},
@@ -10,42 +11,70 @@ use crossterm::{
     terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use fishtank::{
-    event_loop::run_event_loop,
+    cli::Cli,
+    event_loop::{Controls, run_event_loop},
+    headless::run_headless,
+    profile::user_presets,
+    recording::{InputLog, InputSource, Recorder},
     render::{Renderer, info::Info, runner::run_render_loop},
-    sim::{Simulation, runner::run_sim_loop, seed::add_uniform_points, settings::Settings},
+    scene::load_scene,
+    sim::{
+        Simulation, preset::builtin_presets, runner::{Playback, run_sim_loop}, seed, settings::Settings,
+        snapshot::Snapshot,
+    },
 };
 
 fn main() -> anyhow::Result<()> {
+    let mut cli = Cli::parse();
+
+    // a replay recreates the recorded run from the arguments saved in the log
+    let replay = match &cli.replay {
+        Some(path) => {
+            let log = InputLog::load(path)?;
+            cli = Cli::parse_with_recorded_args(&log.args);
+            Some(log)
+        }
+        None => None,
+    };
+
+    let mut settings = cli.settings().unwrap_or_else(|err| {
+        Cli::command()
+            .error(ErrorKind::ValueValidation, format!("{:#}", err))
+            .exit()
+    });
+
+    if cli.headless {
+        let sim = create_sim(&cli, &mut settings, None)?;
+        let inputs = input_source(&cli, &sim, &settings, replay)?;
+        return headless(&cli, sim, settings, inputs);
+    }
+
     let (cols, rows) = terminal::size().unwrap();
 
     let renderer = Renderer::new(rows as usize, cols as usize);
 
-    let mut sim = Simulation::new(cols as f64, 2. * rows as f64);
-    let settings = Settings::default();
+    let sim = create_sim(&cli, &mut settings, Some((cols as f64, 2. * rows as f64)))?;
+    let inputs = input_source(&cli, &sim, &settings, replay)?;
+    let recording = inputs.recording();
 
     // start terminal
     execute!(stdout(), Hide, EnableMouseCapture, EnterAlternateScreen)?;
     crossterm::terminal::enable_raw_mode()?;
     execute!(stdout(), Clear(ClearType::All))?;
 
-    // seed the sim with random particles
-    add_uniform_points(
-        &mut sim,
-        settings.particle_count(),
-        cols as f64,
-        rows as f64 * 2.0,
-    );
-
     let sim = Arc::new(Mutex::new(sim));
     let settings = Arc::new(Mutex::new(settings));
     let renderer = Arc::new(Mutex::new(renderer));
     let info = Arc::new(Mutex::new(Info::default()));
+    let playback = Arc::new(Mutex::new(Playback::default()));
 
     // start the sim thread
     let sim_clone = sim.clone();
     let settings_clone = settings.clone();
+    let playback_clone = playback.clone();
+    let timestep = cli.timestep();
     std::thread::spawn(move || {
-        run_sim_loop(sim_clone, settings_clone);
+        run_sim_loop(sim_clone, settings_clone, playback_clone, timestep, inputs);
     });
 
     // start the render thread
@@ -53,12 +82,29 @@ fn main() -> anyhow::Result<()> {
     let settings_clone = settings.clone();
     let renderer_clone = renderer.clone();
     let info_clone = info.clone();
+    let playback_clone = playback.clone();
+    let fps = cli.fps;
     std::thread::spawn(move || {
-        run_render_loop(sim_clone, settings_clone, renderer_clone, info_clone);
+        run_render_loop(
+            sim_clone,
+            settings_clone,
+            renderer_clone,
+            info_clone,
+            playback_clone,
+            fps,
+        );
     });
 
     // start the event loop (in this thread)
-    run_event_loop(sim, settings, renderer, info)?;
+    let profile = cli.profile_path()?;
+    let mut presets = builtin_presets();
+    presets.extend(user_presets());
+    let controls = Controls {
+        profile: &profile,
+        presets: &presets,
+        recording,
+    };
+    run_event_loop(sim, settings, renderer, info, playback, controls)?;
 
     // end terminal
     execute!(stdout(), Show, DisableMouseCapture, LeaveAlternateScreen)?;
@@ -66,3 +112,66 @@ fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// restores the sim from a snapshot if one was given, otherwise seeds a new one, then puts in
+/// the scene
+fn create_sim(
+    cli: &Cli,
+    settings: &mut Settings,
+    terminal_size: Option<(f64, f64)>,
+) -> anyhow::Result<Simulation> {
+    let seed = cli.seed();
+
+    let mut sim = match &cli.snapshot {
+        Some(path) => {
+            let sim = Simulation::from_snapshot(Snapshot::load(path)?, seed);
+            settings.set_particle_count(sim.particles().len());
+            settings.set_fish_count(sim.fish().len());
+            sim
+        }
+        None => {
+            let (width, height) = cli.domain_size(terminal_size);
+            let mut sim = Simulation::with_seed(width, height, seed);
+            seed::seed(&mut sim, cli.seed_pattern, settings.particle_count());
+            sim.sync_fish_count(settings.fish_count());
+            sim
+        }
+    };
+
+    if let Some(scene) = &cli.scene {
+        load_scene(scene, sim.width(), sim.height())?.apply(&mut sim);
+    }
+    Ok(sim)
+}
+
+/// starts recording if asked to, and feeds in the replayed log if there is one
+fn input_source(
+    cli: &Cli,
+    sim: &Simulation,
+    settings: &Settings,
+    replay: Option<InputLog>,
+) -> anyhow::Result<InputSource> {
+    let recorder = match &cli.record {
+        Some(path) => Some(Recorder::create(path, &cli.recorded_args(sim, settings))?),
+        None => None,
+    };
+    Ok(InputSource::new(recorder, replay))
+}
+
+fn headless(
+    cli: &Cli,
+    mut sim: Simulation,
+    mut settings: Settings,
+    mut inputs: InputSource,
+) -> anyhow::Result<()> {
+    let dt_secs = cli.timestep().substep_secs();
+    run_headless(&mut sim, &mut settings, &mut inputs, cli.steps, dt_secs, &cli.out)?;
+
+    println!(
+        "ran {} steps with seed {}, results written to {}",
+        cli.steps,
+        sim.seed(),
+        cli.out.display()
+    );
+    Ok(())
+}
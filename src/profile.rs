@@ -0,0 +1,248 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, anyhow};
+use toml::{Table, Value};
+
+use crate::sim::{
+    preset::Preset,
+    settings::{Settings, choice_name, parse_choice},
+};
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+// settings picked from a list rather than set to a number, which profiles store by name, and
+// which presets made from profiles leave alone
+const CHOICE_KEYS: [&str; 9] = [
+    "pressure",
+    "eos",
+    "negative-pressure",
+    "solver",
+    "integrator",
+    "left",
+    "right",
+    "top",
+    "bottom",
+];
+
+/// Directory that named profiles are stored in, e.g. `~/.config/fishtank/profiles`.
+pub fn profiles_dir() -> anyhow::Result<PathBuf> {
+    let config_dir = dirs::config_dir().ok_or_else(|| anyhow!("no config directory found"))?;
+    Ok(config_dir.join("fishtank").join("profiles"))
+}
+
+/// Resolves a profile name to a file. Anything that looks like a path is used as-is,
+/// otherwise the name is looked up in [`profiles_dir`].
+pub fn profile_path(name: &str) -> anyhow::Result<PathBuf> {
+    let path = Path::new(name);
+    if path.extension().is_some() || path.components().count() > 1 {
+        return Ok(path.to_path_buf());
+    }
+    Ok(profiles_dir()?.join(format!("{}.toml", name)))
+}
+
+pub fn load_profile(path: &Path, settings: &mut Settings) -> anyhow::Result<()> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    apply_profile(&contents, settings).with_context(|| format!("loading {}", path.display()))
+}
+
+pub fn save_profile(path: &Path, settings: &Settings) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, profile_to_string(settings))
+        .with_context(|| format!("writing {}", path.display()))
+}
+
+/// Every profile in [`profiles_dir`], as presets named after their files. Profiles that can't
+/// be read are skipped.
+pub fn user_presets() -> Vec<Preset> {
+    let Ok(entries) = profiles_dir().and_then(|dir| Ok(fs::read_dir(dir)?)) else {
+        return Vec::new();
+    };
+
+    let mut presets = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "toml" {
+                return None;
+            }
+            let name = path.file_stem()?.to_string_lossy().to_string();
+            let values = parse_profile(&fs::read_to_string(&path).ok()?).ok()?;
+            Some(Preset::new(name, values))
+        })
+        .collect::<Vec<_>>();
+    presets.sort_by(|a, b| a.name.cmp(&b.name));
+    presets
+}
+
+// the numeric parameters in a profile
+fn parse_profile(contents: &str) -> anyhow::Result<Vec<(String, f64)>> {
+    let table = contents.parse::<Table>()?;
+
+    table
+        .into_iter()
+        .filter(|(key, _)| !CHOICE_KEYS.contains(&key.as_str()))
+        .map(|(key, value)| match value {
+            Value::Float(v) => Ok((key, v)),
+            Value::Integer(v) => Ok((key, v as f64)),
+            _ => Err(anyhow!("{} must be a number", key)),
+        })
+        .collect()
+}
+
+fn apply_profile(contents: &str, settings: &mut Settings) -> anyhow::Result<()> {
+    for (key, value) in parse_profile(contents)? {
+        settings.set_by_key(&key, value)?;
+    }
+
+    let table = contents.parse::<Table>()?;
+    let mut boundaries = settings.boundaries();
+    for key in CHOICE_KEYS {
+        let Some(value) = table.get(key) else {
+            continue;
+        };
+        let name = || {
+            value
+                .as_str()
+                .ok_or_else(|| anyhow!("{} must be a name", key))
+        };
+
+        match key {
+            "pressure" => settings.set_pressure_model(parse_choice(name()?)?),
+            "eos" => settings.set_eos(parse_choice(name()?)?),
+            "negative-pressure" => settings.set_negative_pressure(
+                value
+                    .as_bool()
+                    .ok_or_else(|| anyhow!("{} must be true or false", key))?,
+            ),
+            "solver" => settings.set_solver(parse_choice(name()?)?),
+            "integrator" => settings.set_integrator(parse_choice(name()?)?),
+            "left" => boundaries.left = parse_choice(name()?)?,
+            "right" => boundaries.right = parse_choice(name()?)?,
+            "top" => boundaries.top = parse_choice(name()?)?,
+            "bottom" => boundaries.bottom = parse_choice(name()?)?,
+            _ => unreachable!("{} is not a choice", key),
+        }
+    }
+    settings.set_boundaries(boundaries)
+}
+
+fn profile_to_string(settings: &Settings) -> String {
+    let mut table = Settings::KEYS
+        .iter()
+        .zip(settings.params())
+        .map(|(key, param)| (key.to_string(), Value::Float(*param.value())))
+        .collect::<Table>();
+
+    let boundaries = settings.boundaries();
+    let choices = [
+        ("pressure", choice_name(&settings.pressure_model())),
+        ("eos", choice_name(&settings.eos())),
+        ("solver", choice_name(&settings.solver())),
+        ("integrator", choice_name(&settings.integrator())),
+        ("left", choice_name(&boundaries.left)),
+        ("right", choice_name(&boundaries.right)),
+        ("top", choice_name(&boundaries.top)),
+        ("bottom", choice_name(&boundaries.bottom)),
+    ];
+    for (key, name) in choices {
+        table.insert(key.to_string(), Value::String(name));
+    }
+    table.insert(
+        "negative-pressure".to_string(),
+        Value::Boolean(settings.negative_pressure()),
+    );
+    table.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{
+        boundary::{Boundaries, Boundary},
+        eos::Eos,
+        integrator::Integrator,
+        pressure::PressureModel,
+        solver::Solver,
+    };
+
+    #[test]
+    fn round_trip() {
+        let mut settings = Settings::default();
+        settings.set_by_key("viscosity", 12.5).unwrap();
+        settings.set_by_key("particles", 2000.).unwrap();
+
+        let mut loaded = Settings::default();
+        apply_profile(&profile_to_string(&settings), &mut loaded).unwrap();
+
+        assert_eq!(loaded.viscosity(), 12.5);
+        assert_eq!(loaded.particle_count(), 2000);
+    }
+
+    #[test]
+    fn round_trip_choices() {
+        let mut settings = Settings::default();
+        settings.set_pressure_model(PressureModel::DoubleDensity);
+        settings.set_eos(Eos::Tait);
+        settings.set_negative_pressure(true);
+        settings.set_solver(Solver::Pbf);
+        settings.set_integrator(Integrator::Leapfrog);
+        let boundaries = Boundaries {
+            left: Boundary::Periodic,
+            right: Boundary::Periodic,
+            top: Boundary::Open,
+            bottom: Boundary::Sticky,
+        };
+        settings.set_boundaries(boundaries).unwrap();
+
+        let mut loaded = Settings::default();
+        apply_profile(&profile_to_string(&settings), &mut loaded).unwrap();
+
+        assert_eq!(loaded.pressure_model(), PressureModel::DoubleDensity);
+        assert_eq!(loaded.eos(), Eos::Tait);
+        assert!(loaded.negative_pressure());
+        assert_eq!(loaded.solver(), Solver::Pbf);
+        assert_eq!(loaded.integrator(), Integrator::Leapfrog);
+        assert_eq!(loaded.boundaries(), boundaries);
+
+        // and presets made from the profile only take its numbers
+        assert!(parse_profile(&profile_to_string(&settings)).is_ok());
+    }
+
+    #[test]
+    fn partial_profile() {
+        let mut settings = Settings::default();
+        apply_profile("gravity = 5\n", &mut settings).unwrap();
+
+        assert_eq!(settings.gravity(), 5.);
+        assert_eq!(settings.stiffness(), Settings::default().stiffness());
+    }
+
+    #[test]
+    fn reject_bad_values() {
+        let mut settings = Settings::default();
+
+        assert!(apply_profile("gravity = \"lots\"\n", &mut settings).is_err());
+        assert!(apply_profile("gravity = 500\n", &mut settings).is_err());
+        assert!(apply_profile("wind = 1\n", &mut settings).is_err());
+        assert!(apply_profile("solver = \"magic\"\n", &mut settings).is_err());
+        assert!(apply_profile("solver = 1\n", &mut settings).is_err());
+        assert!(apply_profile("left = \"periodic\"\n", &mut settings).is_err());
+    }
+
+    #[test]
+    fn resolve_paths() {
+        assert_eq!(
+            profile_path("honey").unwrap(),
+            profiles_dir().unwrap().join("honey.toml")
+        );
+        assert_eq!(
+            profile_path("./honey.toml").unwrap(),
+            PathBuf::from("./honey.toml")
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{LineWriter, Write},
+    path::Path,
+};
+
+use anyhow::{Context, anyhow};
+
+use crate::sim::{Simulation, input::Input, settings::Settings};
+
+const HEADER: &str = "# fishtank input log v1";
+
+/// A recording of every input applied to a simulation, indexed by the step it was applied
+/// before. The log starts with the command-line arguments needed to recreate the initial
+/// state, one per line:
+///
+/// ```text
+/// # fishtank input log v1
+/// arg --seed
+/// arg 42
+/// 0 param gravity 12
+/// 130 mouse positive 40 12
+/// 181 mouse none
+/// ```
+pub struct InputLog {
+    pub args: Vec<String>,
+    pub events: VecDeque<(u64, Input)>,
+}
+
+impl InputLog {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents =
+            fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        Self::parse(&contents).with_context(|| format!("loading {}", path.display()))
+    }
+
+    fn parse(contents: &str) -> anyhow::Result<Self> {
+        let mut args = Vec::new();
+        let mut events = VecDeque::new();
+
+        for (line_idx, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let context = || format!("line {}", line_idx + 1);
+            match line.split_once(' ') {
+                Some(("arg", arg)) => args.push(arg.to_string()),
+                Some((step, input)) => {
+                    let step = step
+                        .parse::<u64>()
+                        .map_err(|_| anyhow!("`{}` is not a step", step))
+                        .with_context(context)?;
+                    events.push_back((step, input.parse().with_context(context)?));
+                }
+                None => return Err(anyhow!("unrecognized line `{}`", line)).with_context(context),
+            }
+        }
+
+        Ok(Self { args, events })
+    }
+}
+
+/// Writes inputs to a log as they're applied. Each line is flushed straight away so the log
+/// survives the program exiting at any point.
+pub struct Recorder {
+    out: LineWriter<File>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path, args: &[String]) -> anyhow::Result<Self> {
+        let file = File::create(path).with_context(|| format!("writing {}", path.display()))?;
+        let mut out = LineWriter::new(file);
+
+        writeln!(out, "{}", HEADER)?;
+        for arg in args {
+            writeln!(out, "arg {}", arg)?;
+        }
+
+        Ok(Self { out })
+    }
+
+    pub fn record(&mut self, step: u64, input: &Input) -> std::io::Result<()> {
+        writeln!(self.out, "{} {}", step, input)
+    }
+}
+
+/// Decides which inputs are applied before each step: either the ones queued on the
+/// simulation, or those from a replayed log until it runs out. Applied inputs are recorded if
+/// there is a recorder.
+#[derive(Default)]
+pub struct InputSource {
+    recorder: Option<Recorder>,
+    replay: Option<InputLog>,
+}
+
+impl InputSource {
+    pub fn new(recorder: Option<Recorder>, replay: Option<InputLog>) -> Self {
+        Self { recorder, replay }
+    }
+
+    pub fn recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    pub fn replaying(&self) -> bool {
+        self.replay
+            .as_ref()
+            .is_some_and(|log| !log.events.is_empty())
+    }
+
+    pub fn apply(&mut self, sim: &mut Simulation, settings: &mut Settings) {
+        let step = sim.step();
+        let mut inputs = sim.take_inputs();
+
+        if let Some(log) = &mut self.replay {
+            // live inputs are ignored while a replay is in progress
+            if !log.events.is_empty() {
+                inputs.clear();
+            }
+            while log.events.front().is_some_and(|(s, _)| *s <= step) {
+                let (_, input) = log.events.pop_front().unwrap();
+                inputs.push(input);
+            }
+        }
+
+        for input in inputs {
+            if let Some(recorder) = &mut self.recorder {
+                // stop recording rather than keep writing a log with holes in it
+                if recorder.record(step, &input).is_err() {
+                    self.recorder = None;
+                }
+            }
+            input.apply(sim, settings);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::MouseForce;
+
+    #[test]
+    fn parse_log() {
+        let log = InputLog::parse(
+            "# fishtank input log v1\narg --seed\narg 42\n\n0 param gravity 12\n130 mouse none\n",
+        )
+        .unwrap();
+
+        assert_eq!(log.args, ["--seed", "42"]);
+        assert_eq!(
+            Vec::from(log.events),
+            [
+                (0, Input::Param { idx: 1, value: 12. }),
+                (130, Input::Mouse(MouseForce::None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn report_bad_line() {
+        let err = InputLog::parse("0 mouse none\nsoon mouse none\n")
+            .err()
+            .unwrap();
+        assert_eq!(format!("{:#}", err), "line 2: `soon` is not a step");
+    }
+
+    #[test]
+    fn replay_in_step_order() {
+        let log = InputLog::parse("0 param gravity 12\n2 param gravity 3\n").unwrap();
+        let mut sim = Simulation::with_seed(10., 10., 0);
+        let mut settings = Settings::default();
+
+        let mut source = InputSource::new(None, Some(log));
+        sim.push_input(Input::Param { idx: 1, value: 40. });
+        source.apply(&mut sim, &mut settings);
+        assert_eq!(settings.gravity(), 12.);
+
+        sim.update(0.01, &settings);
+        source.apply(&mut sim, &mut settings);
+        assert_eq!(settings.gravity(), 12.);
+        assert!(source.replaying());
+
+        sim.update(0.01, &settings);
+        source.apply(&mut sim, &mut settings);
+        assert_eq!(settings.gravity(), 3.);
+        assert!(!source.replaying());
+    }
+}
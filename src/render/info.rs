@@ -1,11 +1,23 @@
-const INFO_WIDTH: usize = 22;
+const INFO_WIDTH: usize = 27;
 
 pub struct Info {
     particle_count: usize,
     sim_time_ms: f64,
+    substeps: usize,
+    substep_ms: f64,
     render_time_ms: f64,
     fps: f64,
     avg_density: f64,
+    status: String,
+    paused: bool,
+    rewound_secs: f64,
+    time_scale: f64,
+    tool: &'static str,
+    pressure: &'static str,
+    eos: &'static str,
+    negative_pressure: bool,
+    solver: &'static str,
+    integrator: &'static str,
     visible: bool,
 }
 
@@ -14,9 +26,21 @@ impl Default for Info {
         Self {
             particle_count: 0,
             sim_time_ms: 0.,
+            substeps: 1,
+            substep_ms: 0.,
             render_time_ms: 0.,
             fps: 0.,
             avg_density: 0.,
+            status: String::new(),
+            paused: false,
+            rewound_secs: 0.,
+            time_scale: 1.,
+            tool: "Force",
+            pressure: "Clamped",
+            eos: "Linear",
+            negative_pressure: false,
+            solver: "SPH",
+            integrator: "Euler",
             visible: false,
         }
     }
@@ -28,7 +52,7 @@ impl Info {
     }
 
     pub const fn render_height() -> usize {
-        7 // border + 5 lines + border
+        15 // border + 13 lines + border
     }
 
     pub fn update(
@@ -46,6 +70,44 @@ impl Info {
         self.avg_density = avg_density;
     }
 
+    /// how the sim split up its last step
+    pub fn set_timestep(&mut self, substeps: usize, substep_ms: f64) {
+        self.substeps = substeps;
+        self.substep_ms = substep_ms;
+    }
+
+    pub fn set_playback(&mut self, paused: bool, rewound_secs: f64, time_scale: f64) {
+        self.paused = paused;
+        self.rewound_secs = rewound_secs;
+        self.time_scale = time_scale;
+    }
+
+    /// name of the mouse tool in use
+    pub fn set_tool(&mut self, tool: &'static str) {
+        self.tool = tool;
+    }
+
+    /// names of the pressure model, equation of state, solver and integrator in use
+    pub fn set_model(
+        &mut self,
+        pressure: &'static str,
+        eos: &'static str,
+        negative_pressure: bool,
+        solver: &'static str,
+        integrator: &'static str,
+    ) {
+        self.pressure = pressure;
+        self.eos = eos;
+        self.negative_pressure = negative_pressure;
+        self.solver = solver;
+        self.integrator = integrator;
+    }
+
+    /// sets a short message shown at the bottom of the panel
+    pub fn set_status(&mut self, status: impl Into<String>) {
+        self.status = status.into();
+    }
+
     pub fn toggle_visibility(&mut self) {
         self.visible = !self.visible;
     }
@@ -68,18 +130,38 @@ impl Info {
         out.push('┐');
 
         // info rows
+        let playback = if self.paused && self.rewound_secs > 0. {
+            format!(" Paused -{:.1}s", self.rewound_secs)
+        } else if self.paused {
+            " Paused".to_string()
+        } else {
+            format!(" Running {:.1}x", self.time_scale)
+        };
         let lines = [
+            playback,
             format!(" Particles: {}", self.particle_count),
             format!(" Avg Density: {:.2}", self.avg_density),
             format!(" Sim: {:.1} ms", self.sim_time_ms),
+            format!(" Substeps: {} x {:.1} ms", self.substeps, self.substep_ms),
             format!(" Render: {:.1} ms", self.render_time_ms),
             format!(" FPS: {:.1}", self.fps),
+            format!(" Tool: {}", self.tool),
+            format!(" Pressure: {}", self.pressure),
+            if self.negative_pressure {
+                format!(" EOS: {}, negative", self.eos)
+            } else {
+                format!(" EOS: {}", self.eos)
+            },
+            format!(" Solver: {}", self.solver),
+            format!(" Integrator: {}", self.integrator),
+            format!(" {}", self.status),
         ];
 
         for line in &lines {
-            let mut padded = line.clone();
-            if padded.len() < CONTENT_WIDTH {
-                padded.push_str(&" ".repeat(CONTENT_WIDTH - padded.len()));
+            let mut padded = line.chars().take(CONTENT_WIDTH).collect::<String>();
+            let len = padded.chars().count();
+            if len < CONTENT_WIDTH {
+                padded.push_str(&" ".repeat(CONTENT_WIDTH - len));
             }
             out.push_str(&format!("│{}│", padded));
         }
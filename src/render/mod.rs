@@ -1,4 +1,4 @@
-use crate::sim::{Simulation, settings::Settings};
+use crate::sim::{Simulation, body::Body, obstacle::Obstacle, settings::Settings};
 
 pub mod info;
 pub mod runner;
@@ -7,6 +7,15 @@ use info::Info;
 
 const DITHER_RADIUS: f64 = 0.5;
 
+// fish sprites, facing right and left, for small and big fish
+const SMALL_FISH: [&str; 2] = ["><>", "<><"];
+const BIG_FISH: [&str; 2] = ["><))'>", "<'((><"];
+const BIG_FISH_SIZE: f64 = 1.5;
+
+const BODY_GLYPH: char = '█';
+const OBSTACLE_GLYPH: char = '▒';
+const WALL_GLYPH: char = '▓';
+
 pub struct Renderer {
     rows: usize,
     cols: usize,
@@ -61,22 +70,50 @@ impl Renderer {
             output[row][col] = Some(output[row][col].unwrap_or(0) | 1 << bit);
         }
 
+        // glyphs drawn over the particles
+        let mut glyphs = vec![vec![None; self.cols]; self.rows];
+        // painted walls line up with the cells on screen
+        for (row, cells) in sim.walls().cells().iter().enumerate().take(self.rows) {
+            for (col, solid) in cells.iter().enumerate().take(self.cols) {
+                if *solid {
+                    glyphs[row][col] = Some(WALL_GLYPH);
+                }
+            }
+        }
+        for obstacle in sim.obstacles() {
+            self.fill_obstacle(&mut glyphs, obstacle);
+        }
+        for body in sim.bodies() {
+            self.fill_body(&mut glyphs, body);
+        }
+        for fish in sim.fish() {
+            let sprites = if fish.size() >= BIG_FISH_SIZE {
+                BIG_FISH
+            } else {
+                SMALL_FISH
+            };
+            let sprite = sprites[if fish.facing_right() { 0 } else { 1 }];
+            self.draw_sprite(&mut glyphs, fish.x(), fish.y(), sprite);
+        }
+
         let settings_render = settings.render();
         let info_render = info.render();
 
         output
             .into_iter()
+            .zip(glyphs)
             .enumerate()
-            .flat_map(|(row_idx, row)| {
-                row.into_iter().enumerate().map({
+            .flat_map(|(row_idx, (row, glyph_row))| {
+                row.into_iter().zip(glyph_row).enumerate().map({
                     // FIXME: any way to avoid this??
                     let settings_render = settings_render.clone();
                     let info_render = info_render.clone();
 
-                    move |(col_idx, byte)| {
-                        let var_name = match byte {
-                            None => ' ',
-                            Some(b) => {
+                    move |(col_idx, (byte, glyph))| {
+                        let var_name = match (glyph, byte) {
+                            (Some(glyph), _) => glyph,
+                            (None, None) => ' ',
+                            (None, Some(b)) => {
                                 let v = 0x2800u32 | (b as u32);
                                 char::from_u32(v).unwrap_or(' ')
                             }
@@ -99,8 +136,8 @@ impl Renderer {
                             settings_render
                                 .chars()
                                 .nth(
-                                    row_idx * Settings::render_width() + col_idx - self.cols
-                                        + Settings::render_width(),
+                                    row_idx * Settings::render_width()
+                                        + (col_idx + Settings::render_width() - self.cols),
                                 )
                                 .unwrap_or('X')
                         } else {
@@ -111,4 +148,49 @@ impl Renderer {
             })
             .collect::<String>()
     }
+
+    // fills every cell whose center is inside the body
+    fn fill_body(&self, glyphs: &mut [Vec<Option<char>>], body: &Body) {
+        let reach = body.shape().bounding_radius();
+        let rows = ((body.y() - reach) / 2.).max(0.) as usize..=((body.y() + reach) / 2.) as usize;
+        let cols = (body.x() - reach).max(0.) as usize..=(body.x() + reach) as usize;
+
+        for row in rows.filter(|row| *row < self.rows) {
+            for col in cols.clone().filter(|col| *col < self.cols) {
+                let center = (col as f64 + 0.5, row as f64 * 2. + 1.);
+                if body.distance(center).0 < 0. {
+                    glyphs[row][col] = Some(BODY_GLYPH);
+                }
+            }
+        }
+    }
+
+    // fills every cell whose center is inside (or, for thin walls, within half a cell of) the
+    // obstacle
+    fn fill_obstacle(&self, glyphs: &mut [Vec<Option<char>>], obstacle: &Obstacle) {
+        for (row, glyph_row) in glyphs.iter_mut().enumerate() {
+            for (col, glyph) in glyph_row.iter_mut().enumerate() {
+                let center = (col as f64 + 0.5, row as f64 * 2. + 1.);
+                if obstacle.distance(center).0 < 0.5 {
+                    *glyph = Some(OBSTACLE_GLYPH);
+                }
+            }
+        }
+    }
+
+    // writes `sprite` into the glyph layer, centered on the sim position (x, y)
+    fn draw_sprite(&self, glyphs: &mut [Vec<Option<char>>], x: f64, y: f64, sprite: &str) {
+        let row = (y / 2.) as usize;
+        if row >= self.rows {
+            return;
+        }
+
+        let start = x - sprite.chars().count() as f64 / 2.;
+        for (offset, c) in sprite.chars().enumerate() {
+            let col = start + offset as f64;
+            if col >= 0. && (col as usize) < self.cols {
+                glyphs[row][col as usize] = Some(c);
+            }
+        }
+    }
 }
@@ -8,11 +8,10 @@ use crossterm::{cursor::MoveTo, execute};
 
 use crate::{
     render::{Renderer, info::Info},
-    sim::{Simulation, settings::Settings},
+    sim::{Simulation, runner::Playback, settings::Settings},
 };
 
-const TARGET_FPS: f64 = 60.0;
-const TARGET_FRAME_TIME: Duration = Duration::from_micros((1_000_000.0 / TARGET_FPS) as u64);
+pub const TARGET_FPS: f64 = 60.0;
 const SLEEP_OVERHEAD: Duration = Duration::from_millis(3); // compensate for OS sleep overhead
 const INFO_UPDATE_FREQUENCY: u64 = 50; // update info every N frames
 
@@ -21,7 +20,10 @@ pub fn run_render_loop(
     settings: Arc<Mutex<Settings>>,
     renderer: Arc<Mutex<Renderer>>,
     info: Arc<Mutex<Info>>,
+    playback: Arc<Mutex<Playback>>,
+    target_fps: f64,
 ) {
+    let target_frame_time = Duration::from_secs_f64(1.0 / target_fps);
     let mut stdout = stdout();
     let mut frames = 0;
     let mut framerate: f64 = -1.;
@@ -46,7 +48,21 @@ pub fn run_render_loop(
             let renderer = renderer.lock().unwrap();
             let mut info_lock = info.lock().unwrap();
 
-            // update info
+            // update info, with the pause state kept current every frame
+            let playback = playback.lock().unwrap();
+            info_lock.set_playback(
+                playback.paused(),
+                playback.rewound_secs(),
+                settings.time_scale(),
+            );
+            drop(playback);
+            info_lock.set_model(
+                settings.pressure_model().label(),
+                settings.eos().label(),
+                settings.negative_pressure(),
+                settings.solver().label(),
+                settings.integrator().label(),
+            );
             if frames % INFO_UPDATE_FREQUENCY == 0 {
                 info_lock.update(
                     sim.particles().len(),
@@ -55,6 +71,7 @@ pub fn run_render_loop(
                     framerate,
                     sim.avg_density(),
                 );
+                info_lock.set_timestep(sim.substeps(), sim.substep_secs() * 1000.);
             }
 
             let output = renderer.render(&sim, &settings, &info_lock);
@@ -66,6 +83,6 @@ pub fn run_render_loop(
         stdout.flush().unwrap();
 
         render_time_ms = frame_start.elapsed().as_secs_f64() * 1000.0;
-        std::thread::sleep(TARGET_FRAME_TIME.saturating_sub(frame_start.elapsed() + SLEEP_OVERHEAD));
+        std::thread::sleep(target_frame_time.saturating_sub(frame_start.elapsed() + SLEEP_OVERHEAD));
     }
 }
@@ -0,0 +1,324 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, anyhow, bail};
+use toml::{Table, Value};
+
+use crate::sim::{
+    Simulation,
+    flow::{Drain, Emitter, Source},
+    obstacle::{Mask, Obstacle},
+};
+
+/// scenes that can be loaded by name, laid out to fit whatever size the tank is
+pub const BUILTIN_SCENES: [&str; 5] = ["funnel", "dam", "aquarium", "waterfall", "river"];
+
+const DEFAULT_THICKNESS: f64 = 1.;
+
+const CASTLE: &str = "\
+# # #
+#####
+## ##
+## ##";
+
+/// Everything a scene puts in the tank.
+#[derive(Debug, Default, PartialEq)]
+pub struct Scene {
+    pub obstacles: Vec<Obstacle>,
+    pub emitters: Vec<Emitter>,
+    pub drains: Vec<Drain>,
+}
+
+impl Scene {
+    pub fn apply(self, sim: &mut Simulation) {
+        sim.set_obstacles(self.obstacles);
+        sim.set_flows(self.emitters, self.drains);
+    }
+}
+
+/// Loads a scene, either one of [`BUILTIN_SCENES`] or a TOML file like:
+///
+/// ```toml
+/// [[segment]]
+/// from = [10, 20]
+/// to = [40, 30]
+/// thickness = 1.5  # optional
+///
+/// [[circle]]
+/// center = [50, 40]
+/// radius = 4
+///
+/// [[polygon]]
+/// points = [[10, 10], [20, 10], [15, 20]]
+///
+/// [[mask]]
+/// origin = [60, 40]
+/// art = """
+///  ###
+/// #####
+/// """
+///
+/// [[emitter]]
+/// from = [2, 10]  # or `at = [x, y]` for a single point
+/// to = [2, 20]
+/// rate = 300      # particles per second
+/// direction = 0   # degrees clockwise from pointing right
+/// speed = 15
+///
+/// [[drain]]
+/// from = [70, 40] # opposite corners of the region
+/// to = [80, 48]
+/// ```
+pub fn load_scene(name: &str, width: f64, height: f64) -> anyhow::Result<Scene> {
+    if let Some(scene) = builtin_scene(name, width, height) {
+        return Ok(scene);
+    }
+
+    let path = Path::new(name);
+    if !path.exists() && path.extension().is_none() {
+        bail!(
+            "unknown scene `{}`, expected one of {} or a scene file",
+            name,
+            BUILTIN_SCENES.join(", ")
+        );
+    }
+
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    parse_scene(&contents).with_context(|| format!("loading {}", path.display()))
+}
+
+fn builtin_scene(name: &str, w: f64, h: f64) -> Option<Scene> {
+    let segment = |a, b| Obstacle::Segment {
+        a,
+        b,
+        thickness: DEFAULT_THICKNESS,
+    };
+
+    let scene = |obstacles| Scene {
+        obstacles,
+        ..Default::default()
+    };
+
+    let scene = match name {
+        // two sloping walls that pour into a gap in the middle
+        "funnel" => scene(vec![
+            segment((0.1 * w, 0.2 * h), (0.45 * w, 0.5 * h)),
+            segment((0.9 * w, 0.2 * h), (0.55 * w, 0.5 * h)),
+        ]),
+        // a wall holding back the water from the dam seed pattern, low enough to spill over
+        "dam" => {
+            let (left, right) = (w / 3. + 0.5, w / 3. + 2.5);
+            scene(vec![Obstacle::Polygon {
+                vertices: vec![(left, 0.3 * h), (right, 0.3 * h), (right, h), (left, h)],
+            }])
+        }
+        // some rocks and a castle on the bottom
+        "aquarium" => scene(vec![
+            Obstacle::Circle {
+                center: (0.2 * w, h),
+                radius: 5.,
+            },
+            Obstacle::Circle {
+                center: (0.2 * w + 6., h),
+                radius: 3.,
+            },
+            Obstacle::Mask(Mask::new((0.7 * w, h - 8.), CASTLE)),
+        ]),
+        // water pours off a ledge and drains away in the far corner
+        "waterfall" => Scene {
+            obstacles: vec![segment((0., 0.35 * h), (0.3 * w, 0.4 * h))],
+            emitters: vec![Emitter::new(
+                Source::Segment((1., 0.15 * h), (1., 0.3 * h)),
+                400.,
+                0.,
+                12.,
+            )],
+            drains: vec![Drain::new((0.85 * w, 0.85 * h), (w, h))],
+        },
+        // water flows along the bottom, around some rocks, and out the other side
+        "river" => Scene {
+            obstacles: vec![
+                Obstacle::Circle {
+                    center: (0.35 * w, h),
+                    radius: 4.,
+                },
+                Obstacle::Circle {
+                    center: (0.6 * w, h),
+                    radius: 3.,
+                },
+            ],
+            emitters: vec![Emitter::new(
+                Source::Segment((1., 0.65 * h), (1., h - 1.)),
+                400.,
+                0.,
+                10.,
+            )],
+            drains: vec![Drain::new((w - 3., 0.5 * h), (w, h))],
+        },
+        _ => return None,
+    };
+    Some(scene)
+}
+
+fn parse_scene(contents: &str) -> anyhow::Result<Scene> {
+    let table = contents.parse::<Table>()?;
+    let mut scene = Scene::default();
+
+    for (kind, entries) in table {
+        let Value::Array(entries) = entries else {
+            bail!("`{}` must be a list of tables, e.g. [[{}]]", kind, kind);
+        };
+
+        for (idx, entry) in entries.iter().enumerate() {
+            entry
+                .as_table()
+                .ok_or_else(|| anyhow!("not a table"))
+                .and_then(|entry| parse_entry(&kind, entry, &mut scene))
+                .with_context(|| format!("{} {}", kind, idx + 1))?;
+        }
+    }
+    Ok(scene)
+}
+
+fn parse_entry(kind: &str, entry: &Table, scene: &mut Scene) -> anyhow::Result<()> {
+    let field = |name: &str| entry.get(name).ok_or_else(|| anyhow!("missing `{}`", name));
+
+    match kind {
+        "emitter" => {
+            let source = match entry.get("at") {
+                Some(at) => Source::Point(point(at)?),
+                None => Source::Segment(point(field("from")?)?, point(field("to")?)?),
+            };
+            scene.emitters.push(Emitter::new(
+                source,
+                number(field("rate")?)?,
+                number(field("direction")?)?.to_radians(),
+                number(field("speed")?)?,
+            ));
+        }
+        "drain" => scene
+            .drains
+            .push(Drain::new(point(field("from")?)?, point(field("to")?)?)),
+        _ => scene.obstacles.push(parse_obstacle(kind, entry)?),
+    }
+    Ok(())
+}
+
+fn parse_obstacle(kind: &str, entry: &Table) -> anyhow::Result<Obstacle> {
+    let field = |name: &str| entry.get(name).ok_or_else(|| anyhow!("missing `{}`", name));
+
+    let obstacle = match kind {
+        "segment" => Obstacle::Segment {
+            a: point(field("from")?)?,
+            b: point(field("to")?)?,
+            thickness: match entry.get("thickness") {
+                Some(value) => number(value)?,
+                None => DEFAULT_THICKNESS,
+            },
+        },
+        "circle" => Obstacle::Circle {
+            center: point(field("center")?)?,
+            radius: number(field("radius")?)?,
+        },
+        "polygon" => {
+            let Value::Array(points) = field("points")? else {
+                bail!("`points` must be a list of points");
+            };
+            Obstacle::Polygon {
+                vertices: points.iter().map(point).collect::<anyhow::Result<_>>()?,
+            }
+        }
+        "mask" => {
+            let art = field("art")?
+                .as_str()
+                .ok_or_else(|| anyhow!("`art` must be a string"))?;
+            Obstacle::Mask(Mask::new(point(field("origin")?)?, art))
+        }
+        _ => bail!("unknown obstacle"),
+    };
+    Ok(obstacle)
+}
+
+fn number(value: &Value) -> anyhow::Result<f64> {
+    match value {
+        Value::Float(v) => Ok(*v),
+        Value::Integer(v) => Ok(*v as f64),
+        _ => Err(anyhow!("`{}` is not a number", value)),
+    }
+}
+
+fn point(value: &Value) -> anyhow::Result<(f64, f64)> {
+    match value.as_array().map(Vec::as_slice) {
+        Some([x, y]) => Ok((number(x)?, number(y)?)),
+        _ => Err(anyhow!("`{}` is not a point like [x, y]", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_every_obstacle() {
+        let scene = parse_scene(
+            r#"
+            [[segment]]
+            from = [10, 20]
+            to = [40, 30.5]
+
+            [[circle]]
+            center = [50, 40]
+            radius = 4
+
+            [[polygon]]
+            points = [[10, 10], [20, 10], [15, 20]]
+
+            [[mask]]
+            origin = [60, 40]
+            art = "XX"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(scene.obstacles.len(), 4);
+        assert!(scene.obstacles.contains(&Obstacle::Circle {
+            center: (50., 40.),
+            radius: 4.
+        }));
+    }
+
+    #[test]
+    fn parse_flows() {
+        let scene = parse_scene(
+            "[[emitter]]\nat = [5, 5]\nrate = 100\ndirection = 90\nspeed = 2\n\n\
+             [[drain]]\nfrom = [0, 0]\nto = [4, 4]\n",
+        )
+        .unwrap();
+
+        assert_eq!(scene.emitters.len(), 1);
+        let (vx, vy) = scene.emitters[0].velocity();
+        assert!(vx.abs() < 1e-9 && (vy - 2.).abs() < 1e-9);
+        assert!(scene.drains[0].contains((1., 3.)));
+    }
+
+    #[test]
+    fn report_bad_obstacle() {
+        let err = parse_scene("[[circle]]\ncenter = [1, 2]\n").err().unwrap();
+        assert_eq!(format!("{:#}", err), "circle 1: missing `radius`");
+
+        let err = parse_scene("[[segment]]\nfrom = [1]\nto = [2, 3]\n")
+            .err()
+            .unwrap();
+        assert_eq!(
+            format!("{:#}", err),
+            "segment 1: `[1]` is not a point like [x, y]"
+        );
+    }
+
+    #[test]
+    fn builtin_scenes_exist() {
+        for name in BUILTIN_SCENES {
+            assert_ne!(load_scene(name, 80., 48.).unwrap(), Scene::default());
+        }
+    }
+}
@@ -1,28 +1,128 @@
 use std::collections::HashMap;
 
+use rand::{Rng, SeedableRng, rngs::StdRng};
 use rayon::prelude::*;
 
 use particle::Particle;
 
 use crate::sim::{
-    constants::PARTICLE_MASS,
-    kernels::{poly6, spiky_grad, visc_laplacian},
+    body::{Body, BodyForce, BodyKind, MAX_BODIES},
+    boundary::Boundary,
+    constants::{
+        CFL_FORCE, CFL_SPEED, FAUCET_RADIUS, FAUCET_RATE, FAUCET_SPEED, MAX_ADAPTIVE_SUBSTEPS,
+        PARTICLE_MASS, SHAKE_SPEED, SPONGE_RADIUS, TENSILE_CORRECTION, TENSILE_SPACING,
+    },
+    fish::{FISH_PUSH, Fish},
+    flow::{Drain, Emitter},
+    obstacle::{Mask, Obstacle},
+    kernels::{cohesion, near, near_grad, poly6, spiky_grad, visc_laplacian},
+    input::Input,
     settings::Settings,
+    snapshot::Snapshot,
+    solver::Solver,
 };
 
+pub mod body;
+pub mod boundary;
 mod constants;
+pub mod eos;
+pub mod fish;
+pub mod flow;
+pub mod history;
+pub mod input;
+pub mod integrator;
 mod kernels;
+pub mod obstacle;
 mod param;
 mod particle;
+pub mod preset;
+pub mod pressure;
 pub mod runner;
 pub mod seed;
 pub mod settings;
+pub mod shape;
+pub mod snapshot;
+pub mod solver;
 
 type GridPoint = (i64, i64);
+// a force, velocity or other vector on the plane
+type Vec2 = (f64, f64);
 
+/// How particles are hashed into cells about a smoothing radius across. Cells wrap around
+/// periodic edges, so particles near one edge see their neighbors just across the other.
+struct Grid {
+    cell_size: (f64, f64),
+    size: (f64, f64),
+    // number of cells across each direction that wraps around
+    wrap: (Option<i64>, Option<i64>),
+}
+
+impl Grid {
+    fn new(size: (f64, f64), settings: &Settings) -> Self {
+        let (wrap_x, wrap_y) = settings.boundaries().periodic();
+        let axis = |periodic: bool, len: f64| {
+            // periodic directions fit a whole number of cells, and need at least three so that
+            // no cell is its own neighbor twice over
+            let count = (len / settings.cell_size()).floor() as i64;
+            if periodic && count >= 3 {
+                (len / count as f64, Some(count))
+            } else {
+                (settings.cell_size(), None)
+            }
+        };
+        let (x, y) = (axis(wrap_x, size.0), axis(wrap_y, size.1));
+
+        Self {
+            cell_size: (x.0, y.0),
+            size,
+            wrap: (x.1, y.1),
+        }
+    }
+
+    fn key(&self, x: f64, y: f64) -> GridPoint {
+        self.wrap_key((
+            (x / self.cell_size.0).floor() as i64,
+            (y / self.cell_size.1).floor() as i64,
+        ))
+    }
+
+    fn wrap_key(&self, key: GridPoint) -> GridPoint {
+        (
+            self.wrap.0.map_or(key.0, |count| key.0.rem_euclid(count)),
+            self.wrap.1.map_or(key.1, |count| key.1.rem_euclid(count)),
+        )
+    }
+
+    /// the cell at `key` and the eight around it
+    fn neighbors(&self, key: GridPoint) -> impl Iterator<Item = GridPoint> + '_ {
+        [-1, 0, 1].into_iter().flat_map(move |x_offset| {
+            [-1, 0, 1]
+                .into_iter()
+                .map(move |y_offset| self.wrap_key((key.0 + x_offset, key.1 + y_offset)))
+        })
+    }
+
+    /// displacement from `b` to `a`, going the short way around periodic edges
+    fn disp(&self, a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+        let mut disp = (a.0 - b.0, a.1 - b.1);
+        if self.wrap.0.is_some() {
+            disp.0 -= self.size.0 * (disp.0 / self.size.0).round();
+        }
+        if self.wrap.1.is_some() {
+            disp.1 -= self.size.1 * (disp.1 / self.size.1).round();
+        }
+        disp
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum MouseForce {
     Positive { x: f64, y: f64 },
     Negative { x: f64, y: f64 },
+    /// pours new particles out at the cursor
+    Faucet { x: f64, y: f64 },
+    /// soaks up particles under the cursor
+    Sponge { x: f64, y: f64 },
     None,
 }
 
@@ -45,45 +145,123 @@ pub struct Simulation {
     height: f64,
 
     particles: Vec<Particle>,
+    fish: Vec<Fish>,
+    bodies: Vec<Body>,
+    obstacles: Vec<Obstacle>,
+    // walls painted in by hand, one cell per character on screen
+    walls: Mask,
+    emitters: Vec<Emitter>,
+    drains: Vec<Drain>,
+
+    // all randomness in the sim comes from here, so a seed reproduces a run exactly
+    seed: u64,
+    rng: StdRng,
 
     // FIXME: make this private
     pub mouse_force: MouseForce,
 
+    // number of updates run so far, and inputs waiting to be applied before the next one
+    step: u64,
+    pending_inputs: Vec<Input>,
+    // fraction of a particle the faucet has left to pour
+    faucet_carry: f64,
+
+    // how the last update was split up, and the largest acceleration of its last substep
+    substeps: usize,
+    substep_secs: f64,
+    max_accel: f64,
+
     last_frame_ms: f64,
     avg_density: f64,
 }
 
 impl Simulation {
     pub fn new(width: f64, height: f64) -> Self {
+        Self::with_seed(width, height, rand::random())
+    }
+
+    pub fn with_seed(width: f64, height: f64, seed: u64) -> Self {
         Self {
             width,
             height,
             particles: Vec::new(),
+            fish: Vec::new(),
+            bodies: Vec::new(),
+            obstacles: Vec::new(),
+            walls: Mask::default(),
+            emitters: Vec::new(),
+            drains: Vec::new(),
+            seed,
+            rng: StdRng::seed_from_u64(seed),
             mouse_force: MouseForce::None,
+            step: 0,
+            pending_inputs: Vec::new(),
+            faucet_carry: 0.,
+            substeps: 1,
+            substep_secs: 0.,
+            max_accel: 0.,
             last_frame_ms: 0.,
             avg_density: 0.,
         }
     }
 
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            width: self.width,
+            height: self.height,
+            mouse_force: self.mouse_force,
+            particles: self.particles.clone(),
+            fish: self.fish.clone(),
+            bodies: self.bodies.clone(),
+            walls: self.walls.clone(),
+        }
+    }
+
+    pub fn from_snapshot(snapshot: Snapshot, seed: u64) -> Self {
+        let mut sim = Self::with_seed(snapshot.width, snapshot.height, seed);
+        sim.restore(&snapshot);
+        sim
+    }
+
+    /// puts the sim back into the state in `snapshot`, e.g. when rewinding
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.width = snapshot.width;
+        self.height = snapshot.height;
+        self.mouse_force = snapshot.mouse_force;
+        self.particles = snapshot.particles.clone();
+        self.fish = snapshot.fish.clone();
+        self.bodies = snapshot.bodies.clone();
+        self.walls = snapshot.walls.clone();
+        // the forces are unknown until the next substep works them out
+        self.max_accel = 0.;
+    }
+
     pub fn add_particle(&mut self, x: f64, y: f64) {
         self.particles.push(Particle::new(x, y, 0., 0.));
     }
 
+    /// queues an input to be applied before the next step
+    pub fn push_input(&mut self, input: Input) {
+        self.pending_inputs.push(input);
+    }
+
+    pub fn take_inputs(&mut self) -> Vec<Input> {
+        std::mem::take(&mut self.pending_inputs)
+    }
+
     pub fn resize(&mut self, width: f64, height: f64) {
         self.width = width;
         self.height = height;
     }
 
     pub fn sync_particle_count(&mut self, target_count: usize) {
-        use rand::Rng;
         let current_count = self.particles.len();
 
         if current_count < target_count {
             // add random particles
-            let mut rng = rand::rng();
             for _ in 0..(target_count - current_count) {
-                let x = rng.random::<f64>() * self.width;
-                let y = rng.random::<f64>() * self.height;
+                let x = self.rng.random::<f64>() * self.width;
+                let y = self.rng.random::<f64>() * self.height;
                 self.add_particle(x, y);
             }
         } else if current_count > target_count {
@@ -92,55 +270,244 @@ impl Simulation {
         }
     }
 
+    pub fn sync_fish_count(&mut self, target_count: usize) {
+        while self.fish.len() < target_count {
+            let x = self.rng.random::<f64>() * self.width;
+            let y = self.rng.random::<f64>() * self.height;
+            let heading = self.rng.random::<f64>() * std::f64::consts::TAU;
+            let size = self.rng.random_range(fish::MIN_SIZE..=fish::MAX_SIZE);
+            self.fish.push(Fish::new(x, y, heading, size));
+        }
+        self.fish.truncate(target_count);
+    }
+
+    /// replaces the static obstacles in the tank
+    pub fn set_obstacles(&mut self, obstacles: Vec<Obstacle>) {
+        self.obstacles = obstacles;
+    }
+
+    /// replaces the sources that pour particles in and the drains that take them out
+    pub fn set_flows(&mut self, emitters: Vec<Emitter>, drains: Vec<Drain>) {
+        self.emitters = emitters;
+        self.drains = drains;
+    }
+
+    /// fills in or clears the wall cell at `col`, `row`, ignoring cells outside the tank
+    pub fn paint_wall(&mut self, col: usize, row: usize, solid: bool) {
+        let (cols, rows) = Mask::grid_size(self.width, self.height);
+        if col < cols && row < rows {
+            self.walls.set(col, row, solid);
+        }
+    }
+
+    /// drops a new body into the tank, making room by removing the oldest if it's full
+    pub fn drop_body(&mut self, kind: BodyKind, x: f64, y: f64) {
+        if self.bodies.len() == MAX_BODIES {
+            self.bodies.remove(0);
+        }
+        self.bodies.push(Body::of_kind(kind, x, y));
+    }
+
+    /// jolts the tank sideways in a random direction and a little upwards, throwing the water
+    /// and bodies the other way
+    pub fn shake(&mut self) {
+        let side = if self.rng.random::<bool>() { 1. } else { -1. };
+        let impulse = (side * SHAKE_SPEED, -0.5 * SHAKE_SPEED);
+
+        // particle velocities point against their motion
+        for particle in &mut self.particles {
+            particle.vel.0 -= impulse.0;
+            particle.vel.1 -= impulse.1;
+        }
+        for body in &mut self.bodies {
+            body.jolt(impulse);
+        }
+    }
+
+    /// Moves the sim forward by `dt_secs`, split into as many substeps as it takes for no
+    /// particle to move or speed up too much in one go.
     pub fn update(&mut self, dt_secs: f64, settings: &Settings) {
         let start_time = std::time::Instant::now();
 
-        // hash particles into a grid
-        let (keys, spatial_hash) = self.build_hash(settings);
+        self.substeps = self.stable_substeps(dt_secs, settings);
+        self.substep_secs = dt_secs / self.substeps as f64;
+        for _ in 0..self.substeps {
+            self.substep(self.substep_secs, settings);
+        }
 
-        // density computation
-        let densities = self.compute_densities(&keys, &spatial_hash, settings);
+        self.step += 1;
 
-        // compute average density
-        self.avg_density = if !densities.is_empty() {
-            densities.iter().sum::<f64>() / densities.len() as f64
+        // compute time
+        let time = start_time.elapsed().as_secs_f64();
+        self.last_frame_ms = time * 1000.;
+    }
+
+    // how many substeps `dt_secs` needs, from the CFL condition on the fastest particle and the
+    // largest acceleration of the last substep (the forces aren't known until it's too late)
+    fn stable_substeps(&self, dt_secs: f64, settings: &Settings) -> usize {
+        let smoothing_radius = settings.smoothing_radius();
+        let max_speed = self
+            .particles
+            .iter()
+            .map(|pt| pt.vel_x().hypot(pt.vel_y()))
+            .fold(0., f64::max);
+
+        let speed_dt = CFL_SPEED * smoothing_radius / max_speed;
+        let force_dt = CFL_FORCE * (smoothing_radius / self.max_accel).sqrt();
+        // artificial viscosity acts as fast as sound crosses the fluid (Monaghan's condition)
+        let artificial_viscosity = settings.artificial_viscosity();
+        let sound_dt = if artificial_viscosity > 0. && settings.solver() == Solver::Sph {
+            let sound_speed = settings.stiffness().sqrt();
+            CFL_SPEED * smoothing_radius / (sound_speed * (1. + 0.6 * artificial_viscosity))
         } else {
-            0.
+            f64::INFINITY
+        };
+        let substeps = (dt_secs / speed_dt.min(force_dt).min(sound_dt)).ceil();
+
+        // NaN and infinity (e.g. nothing moving) both fall through to a single step
+        if substeps.is_finite() {
+            (substeps as usize).clamp(1, MAX_ADAPTIVE_SUBSTEPS)
+        } else {
+            1
+        }
+    }
+
+    fn substep(&mut self, dt_secs: f64, settings: &Settings) {
+        // add or remove particles with the mouse, emitters and drains
+        self.apply_mouse_tools(dt_secs, settings);
+        self.apply_flows(dt_secs, settings);
+
+        // hash particles into a grid
+        let grid = Grid::new((self.width, self.height), settings);
+        let (keys, spatial_hash) = self.build_hash(&grid);
+
+        // fish are carried along by the water around them, so sample it while the hash is around
+        let fluid_vels = self
+            .fish
+            .iter()
+            .map(|fish| self.sample_fluid_vel(fish.x(), fish.y(), &grid, &spatial_hash, settings))
+            .collect::<Vec<_>>();
+
+        // move the particles, working out how hard they push on the bodies along the way
+        let body_forces = match settings.solver() {
+            Solver::Sph => self.sph_step(&grid, keys, spatial_hash, dt_secs, settings),
+            Solver::Pbf => solver::pbf_step(self, &grid, keys, spatial_hash, dt_secs, settings),
         };
 
-        // pressure computation
+        // move the bodies
+        let size = (self.width, self.height);
+        let (gravity, dampening) = (settings.gravity_vec(), settings.dampening());
+        for (body, force) in self.bodies.iter_mut().zip(body_forces) {
+            body.update(force, gravity, dampening, size, settings.boundaries(), dt_secs);
+        }
+
+        // apply boundaries
+        self.apply_boundaries(settings);
+
+        // move the fish
+        fish::update_school(&mut self.fish, &fluid_vels, size, settings.gravity_vec(), dt_secs);
+    }
+
+    // explicit SPH: pressure from density, then forces from pressure, then move
+    fn sph_step(
+        &mut self,
+        grid: &Grid,
+        keys: Vec<GridPoint>,
+        spatial_hash: HashMap<GridPoint, Vec<usize>>,
+        dt_secs: f64,
+        settings: &Settings,
+    ) -> Vec<BodyForce> {
+        // density computation
+        let uses_near = settings.pressure_model().uses_near_density();
+        let (densities, near_densities) =
+            self.compute_densities(grid, &keys, &spatial_hash, uses_near, settings);
+        self.avg_density = average(&densities);
+
+        // pressure computation, as (pressure, near-pressure) pairs
+        let model = settings.pressure_model();
+        let eos = settings.eos();
+        let negative_pressure = settings.negative_pressure();
         let target_density = settings.target_density();
         let stiffness = settings.stiffness();
+        let gamma = settings.tait_gamma();
         let pressures = densities
             .iter()
-            .map(|d| (stiffness * (d - target_density)).max(0.))
+            .zip(&near_densities)
+            .map(|(d, near_d)| {
+                let pressure = eos.pressure(*d, target_density, stiffness, gamma);
+                (
+                    model.pressure(pressure, negative_pressure),
+                    model.near_pressure(*near_d, stiffness),
+                )
+            })
             .collect::<Vec<_>>();
 
         // force computation
-        let forces = self.compute_forces(keys, spatial_hash, &densities, pressures, settings);
+        let (forces, xsph, body_forces) = self.compute_forces(
+            grid,
+            keys,
+            spatial_hash,
+            &densities,
+            Some(&pressures),
+            settings,
+        );
 
         // apply forces to move particles
-        self.apply_forces(dt_secs, densities, forces);
+        self.apply_forces(dt_secs, densities, forces, xsph, settings);
+        body_forces
+    }
 
-        // apply boundaries
-        self.apply_boundaries(settings);
+    fn apply_mouse_tools(&mut self, dt_secs: f64, settings: &Settings) {
+        match self.mouse_force {
+            MouseForce::Faucet { x, y } => {
+                self.faucet_carry += FAUCET_RATE * dt_secs;
+                let room = settings.max_particle_count().saturating_sub(self.particles.len());
+                let count = (self.faucet_carry as usize).min(room);
+                self.faucet_carry = self.faucet_carry.fract();
 
-        // compute time
-        let time = start_time.elapsed().as_secs_f64();
-        self.last_frame_ms = time * 1000.;
+                for _ in 0..count {
+                    // spread out a little so they don't all start in the same place
+                    let angle = self.rng.random::<f64>() * std::f64::consts::TAU;
+                    let dist = self.rng.random::<f64>() * FAUCET_RADIUS;
+                    let (x, y) = (x + dist * angle.cos(), y + dist * angle.sin());
+
+                    // pour downwards; particle velocities point against their motion
+                    self.particles.push(Particle::new(x, y, 0., -FAUCET_SPEED));
+                }
+            }
+            MouseForce::Sponge { x, y } => {
+                self.particles.retain(|p| {
+                    (p.x() - x).powi(2) + (p.y() - y).powi(2) > SPONGE_RADIUS * SPONGE_RADIUS
+                });
+            }
+            _ => self.faucet_carry = 0.,
+        }
     }
 
-    fn build_hash(&mut self, settings: &Settings) -> (Vec<GridPoint>, HashMap<GridPoint, Vec<usize>>) {
-        let cell_size = settings.cell_size();
+    fn apply_flows(&mut self, dt_secs: f64, settings: &Settings) {
+        for emitter in &mut self.emitters {
+            let room = settings.max_particle_count().saturating_sub(self.particles.len());
+            let count = emitter.due(dt_secs).min(room);
+            // particle velocities point against their motion
+            let vel = emitter.velocity();
+            for _ in 0..count {
+                let (x, y) = emitter.spawn_point(&mut self.rng);
+                self.particles.push(Particle::new(x, y, -vel.0, -vel.1));
+            }
+        }
+
+        if !self.drains.is_empty() {
+            let drains = &self.drains;
+            self.particles
+                .retain(|p| !drains.iter().any(|drain| drain.contains((p.x(), p.y()))));
+        }
+    }
+
+    fn build_hash(&mut self, grid: &Grid) -> (Vec<GridPoint>, HashMap<GridPoint, Vec<usize>>) {
         let keys = self
             .particles
             .iter()
-            .map(|particle| {
-                (
-                    (particle.x() / cell_size).floor() as i64,
-                    (particle.y() / cell_size).floor() as i64,
-                )
-            })
+            .map(|particle| grid.key(particle.x(), particle.y()))
             .collect::<Vec<_>>();
 
         // hash particle positions into cells
@@ -156,100 +523,229 @@ impl Simulation {
 
     fn compute_densities(
         &mut self,
+        grid: &Grid,
         keys: &[(i64, i64)],
         spatial_hash: &HashMap<(i64, i64), Vec<usize>>,
+        uses_near: bool,
         settings: &Settings,
-    ) -> Vec<f64> {
+    ) -> (Vec<f64>, Vec<f64>) {
         let smoothing_radius = settings.smoothing_radius();
         let smoothing_radius_sq = settings.smoothing_radius_sq();
 
+        // each particle sums over its neighbors sequentially, in cell-offset then index order,
+        // so the result doesn't depend on how rayon splits up the work
         self.particles
             .par_iter()
             .enumerate()
             .map(|(idx1, pt)| {
                 let key = keys[idx1];
                 let mut density = 0.;
+                let mut near_density = 0.;
 
                 // only do computations in neighboring cells
-                for x_offset in [-1, 0, 1] {
-                    for y_offset in [-1, 0, 1] {
-                        let key = (key.0 + x_offset, key.1 + y_offset);
-                        if let Some(v) = spatial_hash.get(&key) {
-                            for idx2 in v {
-                                let pt2 = &self.particles[*idx2];
-
-                                // restrict attention to neighbors within SMOOTHING_RADIUS
-                                let sq_dist =
-                                    (pt.x() - pt2.x()).powi(2) + (pt.y() - pt2.y()).powi(2);
-                                if sq_dist > smoothing_radius_sq {
-                                    continue;
-                                }
+                for key in grid.neighbors(key) {
+                    if let Some(v) = spatial_hash.get(&key) {
+                        for idx2 in v {
+                            let pt2 = &self.particles[*idx2];
+
+                            // restrict attention to neighbors within SMOOTHING_RADIUS
+                            let disp = grid.disp((pt.x(), pt.y()), (pt2.x(), pt2.y()));
+                            let sq_dist = disp.0.powi(2) + disp.1.powi(2);
+                            if sq_dist > smoothing_radius_sq {
+                                continue;
+                            }
 
-                                density += PARTICLE_MASS * poly6(sq_dist, smoothing_radius, smoothing_radius_sq);
+                            density += PARTICLE_MASS * poly6(sq_dist, smoothing_radius, smoothing_radius_sq);
+                            if uses_near && idx1 != *idx2 {
+                                near_density += PARTICLE_MASS * near(sq_dist.sqrt(), smoothing_radius);
                             }
                         }
                     }
                 }
 
-                density
+                (density, near_density)
             })
-            .collect::<Vec<_>>()
+            .unzip()
+    }
+
+    /// Kernel-weighted average velocity of the fluid at a point, or `None` if there isn't enough
+    /// fluid there to count as being in the water. The velocity points the way the fluid moves.
+    fn sample_fluid_vel(
+        &self,
+        x: f64,
+        y: f64,
+        grid: &Grid,
+        spatial_hash: &HashMap<(i64, i64), Vec<usize>>,
+        settings: &Settings,
+    ) -> Option<(f64, f64)> {
+        let smoothing_radius = settings.smoothing_radius();
+        let smoothing_radius_sq = settings.smoothing_radius_sq();
+
+        let mut density = 0.;
+        let mut vel = (0., 0.);
+        for key in grid.neighbors(grid.key(x, y)) {
+            for idx in spatial_hash.get(&key).into_iter().flatten() {
+                let pt = &self.particles[*idx];
+                let disp = grid.disp((x, y), (pt.x(), pt.y()));
+                let sq_dist = disp.0.powi(2) + disp.1.powi(2);
+                if sq_dist > smoothing_radius_sq {
+                    continue;
+                }
+
+                let weight = PARTICLE_MASS * poly6(sq_dist, smoothing_radius, smoothing_radius_sq);
+                density += weight;
+                // particle velocities point against their motion
+                vel.0 -= weight * pt.vel_x();
+                vel.1 -= weight * pt.vel_y();
+            }
+        }
+
+        (density >= 0.5 * settings.target_density()).then(|| (vel.0 / density, vel.1 / density))
     }
 
+    // forces on each particle, the XSPH corrections to how fast each one moves, and the forces
+    // on each body. Without `pressures`, as (pressure, near-pressure) pairs, particles only push
+    // on each other and on the bodies as much as the solver makes them.
     fn compute_forces(
         &mut self,
+        grid: &Grid,
         keys: Vec<(i64, i64)>,
         spatial_hash: HashMap<(i64, i64), Vec<usize>>,
         densities: &[f64],
-        pressures: Vec<f64>,
+        pressures: Option<&[(f64, f64)]>,
         settings: &Settings,
-    ) -> Vec<(f64, f64)> {
-        let gravity = settings.gravity();
+    ) -> (Vec<Vec2>, Vec<Vec2>, Vec<BodyForce>) {
+        let gravity = settings.gravity_vec();
         let smoothing_radius = settings.smoothing_radius();
         let viscosity = settings.viscosity();
+        let cohesion_strength = settings.cohesion();
+        let smoothing_radius_sq = settings.smoothing_radius_sq();
+        // where pressure pulls, neighbors closer than the usual spacing are pushed apart a
+        // little (Monaghan's artificial stress), so the fluid doesn't clump into pairs
+        let tensile_reference = poly6(
+            (TENSILE_SPACING * smoothing_radius).powi(2),
+            smoothing_radius,
+            smoothing_radius_sq,
+        );
+        let target_density = settings.target_density();
         let mouse_force_strength = settings.mouse_force_strength();
         let mouse_force_radius = settings.mouse_force_radius();
+        let xsph_strength = settings.xsph();
+        // artificial viscosity damps pressure waves, so it goes along with pressure
+        let artificial_viscosity = if pressures.is_some() {
+            settings.artificial_viscosity()
+        } else {
+            0.
+        };
+        // how fast pressure waves cross the fluid, for a linear equation of state
+        let sound_speed = settings.stiffness().sqrt();
+        let pressure_at = |idx: usize| pressures.map_or(0., |pressures| pressures[idx].0);
 
-        self.particles
+        let (forces, xsph): (Vec<_>, Vec<_>) = self
+            .particles
             .par_iter()
             .enumerate()
             .map(|(idx1, pt)| {
                 let key = keys[idx1];
-                let mut force = (0., -gravity);
+                // forces on particles point against their motion
+                let mut force = (-gravity.0, -gravity.1);
+                let mut xsph = (0., 0.);
 
                 // only do computations in neighboring cells
-                for x_offset in [-1, 0, 1] {
-                    for y_offset in [-1, 0, 1] {
-                        let key = (key.0 + x_offset, key.1 + y_offset);
-                        if let Some(v) = spatial_hash.get(&key) {
-                            for idx2 in v {
-                                let pt2 = &self.particles[*idx2];
-
-                                // restrict attention to neighbors within SMOOTHING_RADIUS, excluding self,
-                                let disp = (pt.x() - pt2.x(), pt.y() - pt2.y());
-                                let dist = (disp.0.powi(2) + disp.1.powi(2)).sqrt();
-                                if idx1 == *idx2 || dist > smoothing_radius || dist <= 0. {
-                                    continue;
-                                }
+                for key in grid.neighbors(key) {
+                    if let Some(v) = spatial_hash.get(&key) {
+                        for idx2 in v {
+                            let pt2 = &self.particles[*idx2];
 
-                                // pressure force
+                            // restrict attention to neighbors within SMOOTHING_RADIUS, excluding self,
+                            let disp = grid.disp((pt.x(), pt.y()), (pt2.x(), pt2.y()));
+                            let dist = (disp.0.powi(2) + disp.1.powi(2)).sqrt();
+                            if idx1 == *idx2 || dist > smoothing_radius || dist <= 0. {
+                                continue;
+                            }
+
+                            if let Some(pressures) = pressures {
+                                // pressure force, unless the solver works out pressure itself
+                                let (pressure1, pressure2) = (pressures[idx1].0, pressures[*idx2].0);
+                                let tensile = TENSILE_CORRECTION
+                                    * (pressure1.min(0.) + pressure2.min(0.)).abs()
+                                    * (poly6(dist * dist, smoothing_radius, smoothing_radius_sq)
+                                        / tensile_reference)
+                                        .powi(4);
                                 let pressure_force_coeff = PARTICLE_MASS
-                                    * (pressures[idx1] + pressures[*idx2])
+                                    * (pressure1 + pressure2 + tensile)
                                     * spiky_grad(dist, smoothing_radius)
                                     / (2. * densities[*idx2] * dist);
 
                                 force.0 += pressure_force_coeff * disp.0;
                                 force.1 += pressure_force_coeff * disp.1;
 
-                                // viscosity force
-                                let vel_diff = (pt2.vel_x() - pt.vel_x(), pt2.vel_y() - pt.vel_y());
+                                // near-pressure between close neighbors, zero unless the model uses it
+                                let near_pressure = pressures[idx1].1 + pressures[*idx2].1;
+                                if near_pressure > 0. {
+                                    let near_force_coeff = PARTICLE_MASS
+                                        * near_pressure
+                                        * near_grad(dist, smoothing_radius)
+                                        / (2. * densities[*idx2] * dist);
+
+                                    force.0 += near_force_coeff * disp.0;
+                                    force.1 += near_force_coeff * disp.1;
+                                }
+                            }
 
-                                let visc_force_coeff =
-                                    viscosity * PARTICLE_MASS * visc_laplacian(dist, smoothing_radius)
-                                        / densities[*idx2];
+                            // viscosity force
+                            let vel_diff = (pt2.vel_x() - pt.vel_x(), pt2.vel_y() - pt.vel_y());
+
+                            let visc_force_coeff =
+                                viscosity * PARTICLE_MASS * visc_laplacian(dist, smoothing_radius)
+                                    / densities[*idx2];
+
+                            force.0 += visc_force_coeff * vel_diff.0;
+                            force.1 += visc_force_coeff * vel_diff.1;
+
+                            // artificial viscosity (after Monaghan) only between neighbors closing
+                            // in on each other, which damps out noise without slowing the flow
+                            let closing = vel_diff.0 * disp.0 + vel_diff.1 * disp.1;
+                            let mean_density = 0.5 * (densities[idx1] + densities[*idx2]);
+                            if artificial_viscosity > 0. && closing < 0. {
+                                let mu = smoothing_radius * closing
+                                    / (dist * dist + 0.01 * smoothing_radius_sq);
+                                let pi = -artificial_viscosity * sound_speed * smoothing_radius * mu
+                                    / mean_density;
+                                let art_visc_coeff = PARTICLE_MASS
+                                    * pi
+                                    * densities[idx1]
+                                    * spiky_grad(dist, smoothing_radius)
+                                    / dist;
 
-                                force.0 += visc_force_coeff * vel_diff.0;
-                                force.1 += visc_force_coeff * vel_diff.1;
+                                force.0 += art_visc_coeff * disp.0;
+                                force.1 += art_visc_coeff * disp.1;
+                            }
+
+                            // XSPH moves each particle partway at the average velocity of its
+                            // neighbors, so nearby particles move together
+                            if xsph_strength > 0. {
+                                let xsph_coeff = xsph_strength
+                                    * PARTICLE_MASS
+                                    * poly6(dist * dist, smoothing_radius, smoothing_radius_sq)
+                                    / mean_density;
+
+                                xsph.0 += xsph_coeff * vel_diff.0;
+                                xsph.1 += xsph_coeff * vel_diff.1;
+                            }
+
+                            // cohesion pulls neighbors together, more strongly where the fluid is
+                            // thin, so that the surface beads up into droplets
+                            if cohesion_strength > 0. {
+                                let correction = 2. * target_density / (densities[idx1] + densities[*idx2]);
+                                let cohesion_coeff = cohesion_strength
+                                    * PARTICLE_MASS
+                                    * correction
+                                    * cohesion(dist, smoothing_radius)
+                                    / dist;
+
+                                force.0 += cohesion_coeff * disp.0;
+                                force.1 += cohesion_coeff * disp.1;
                             }
                         }
                     }
@@ -284,20 +780,101 @@ impl Simulation {
                             force.1 -= coeff / 30.0 * pt.vel_y();
                         }
                     }
-                    MouseForce::None => {}
+                    MouseForce::Faucet { .. } | MouseForce::Sponge { .. } | MouseForce::None => {}
+                }
+
+                // fish push the water out of their way
+                for fish in &self.fish {
+                    let disp = (fish.x() - pt.x(), fish.y() - pt.y());
+                    let dist = (disp.0.powi(2) + disp.1.powi(2)).sqrt();
+                    let coeff = FISH_PUSH * (fish.radius() - dist).max(0.) / densities[idx1];
+
+                    force.0 += coeff * disp.0;
+                    force.1 += coeff * disp.1;
+                }
+
+                // bodies keep the water out, and drag it along with them
+                for body in &self.bodies {
+                    if let Some(contact) = self.body_contact(body, pt, pressure_at(idx1), settings) {
+                        // forces on particles point against their motion
+                        force.0 -= contact.0;
+                        force.1 -= contact.1;
+                    }
                 }
 
-                force
+                (force, xsph)
+            })
+            .unzip();
+
+        // the fluid pushes back on the bodies just as hard. this is summed in particle order
+        // so that the result is the same every run
+        let body_forces = self
+            .bodies
+            .iter()
+            .map(|body| {
+                let mut total = ((0., 0.), 0.);
+                for (idx, pt) in self.particles.iter().enumerate() {
+                    let Some(contact) = self.body_contact(body, pt, pressure_at(idx), settings)
+                    else {
+                        continue;
+                    };
+
+                    // the particle's acceleration is its force over its density
+                    let force = (
+                        -PARTICLE_MASS * contact.0 / densities[idx],
+                        -PARTICLE_MASS * contact.1 / densities[idx],
+                    );
+                    let r = (pt.x() - body.x(), pt.y() - body.y());
+                    total.0.0 += force.0;
+                    total.0.1 += force.1;
+                    total.1 += r.0 * force.1 - r.1 * force.0;
+                }
+                total
             })
-            .collect::<Vec<_>>()
+            .collect::<Vec<_>>();
+
+        (forces, xsph, body_forces)
+    }
+
+    // force on a particle from touching a body, in the direction it's moving
+    fn body_contact(
+        &self,
+        body: &Body,
+        pt: &Particle,
+        pressure: f64,
+        settings: &Settings,
+    ) -> Option<(f64, f64)> {
+        let range = settings.smoothing_radius();
+        let pos = (pt.x(), pt.y());
+        if !body.near(pos, range) {
+            return None;
+        }
+
+        // bodies only feel the fluid pushing, even where its pressure pulls
+        let vel = (-pt.vel_x(), -pt.vel_y());
+        body.contact_force(pos, vel, pressure.max(0.), settings.viscosity(), range)
     }
 
-    fn apply_forces(&mut self, dt_secs: f64, densities: Vec<f64>, forces: Vec<(f64, f64)>) {
+    fn apply_forces(
+        &mut self,
+        dt_secs: f64,
+        densities: Vec<f64>,
+        forces: Vec<Vec2>,
+        xsph: Vec<Vec2>,
+        settings: &Settings,
+    ) {
+        let integrator = settings.integrator();
+        self.max_accel = 0.;
         for (idx, p) in self.particles.iter_mut().enumerate() {
             let density = densities[idx];
-            let force = (forces[idx].0 / density, forces[idx].1 / density);
-            p.update_vel(force, dt_secs);
-            p.update_pos(dt_secs);
+            let accel = (forces[idx].0 / density, forces[idx].1 / density);
+            self.max_accel = self.max_accel.max(accel.0.hypot(accel.1));
+            p.integrate(integrator, accel, dt_secs);
+
+            // XSPH only changes where the particle goes, not its momentum, and like velocity it
+            // points against the motion
+            p.set_x(p.x() - xsph[idx].0 * dt_secs);
+            p.set_y(p.y() - xsph[idx].1 * dt_secs);
         }
     }
 
@@ -305,39 +882,380 @@ impl Simulation {
         let width = self.width;
         let height = self.height;
         let dampening = settings.dampening();
+        let boundaries = settings.boundaries();
 
         for particle in self.particles.iter_mut() {
-            if particle.x() < 0. {
-                particle.set_x(-particle.x());
-                particle.vel.0 *= -dampening;
-            }
+            let (mut x, mut y) = (particle.x(), particle.y());
+            let (mut vel_x, mut vel_y) = particle.vel;
 
-            if particle.y() < 0. {
-                particle.set_y(-particle.y());
-                particle.vel.1 *= -dampening;
+            if x < 0. {
+                cross_edge(boundaries.left, &mut x, (&mut vel_x, &mut vel_y), 0., width, dampening);
+            }
+            if y < 0. {
+                cross_edge(boundaries.top, &mut y, (&mut vel_y, &mut vel_x), 0., height, dampening);
+            }
+            if x > width {
+                cross_edge(boundaries.right, &mut x, (&mut vel_x, &mut vel_y), width, width, dampening);
             }
+            if y > height {
+                cross_edge(boundaries.bottom, &mut y, (&mut vel_y, &mut vel_x), height, height, dampening);
+            }
+
+            particle.set_x(x);
+            particle.set_y(y);
+            particle.vel = (vel_x, vel_y);
 
-            if particle.x() > width {
-                particle.set_x(width - (particle.x() - width));
-                particle.vel.0 *= -dampening;
+            // obstacles and walls bounce particles off the same way the edges do
+            for obstacle in &self.obstacles {
+                let (dist, normal) = obstacle.distance((particle.x(), particle.y()));
+                if dist < 0. {
+                    particle.collide(dist, normal, (0., 0.), dampening);
+                }
+            }
+            let (dist, normal) = self.walls.distance((particle.x(), particle.y()));
+            if dist < 0. {
+                particle.collide(dist, normal, (0., 0.), dampening);
             }
 
-            if particle.y() > height {
-                particle.set_y(height - (particle.y() - height));
-                particle.vel.1 *= -dampening;
+            // nothing gets inside a body
+            for body in &self.bodies {
+                let pos = (particle.x(), particle.y());
+                if !body.near(pos, 0.) {
+                    continue;
+                }
+                let (dist, normal) = body.distance(pos);
+                if dist < 0. {
+                    particle.collide(dist, normal, body.velocity_at(pos), dampening);
+                }
             }
         }
+
+        // anything still outside went through an open edge
+        if [boundaries.left, boundaries.right, boundaries.top, boundaries.bottom].contains(&Boundary::Open) {
+            self.particles
+                .retain(|p| (0. ..=width).contains(&p.x()) && (0. ..=height).contains(&p.y()));
+            self.bodies
+                .retain(|b| (0. ..=width).contains(&b.x()) && (0. ..=height).contains(&b.y()));
+        }
+    }
+
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+
+    pub fn height(&self) -> f64 {
+        self.height
+    }
+
+    pub fn step(&self) -> u64 {
+        self.step
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
     }
 
     pub fn particles(&self) -> &[Particle] {
         &self.particles
     }
 
+    pub fn fish(&self) -> &[Fish] {
+        &self.fish
+    }
+
+    pub fn bodies(&self) -> &[Body] {
+        &self.bodies
+    }
+
+    pub fn obstacles(&self) -> &[Obstacle] {
+        &self.obstacles
+    }
+
+    pub fn walls(&self) -> &Mask {
+        &self.walls
+    }
+
     pub fn last_frame_ms(&self) -> f64 {
         self.last_frame_ms
     }
 
+    /// how many substeps the last update was split into
+    pub fn substeps(&self) -> usize {
+        self.substeps
+    }
+
+    pub fn substep_secs(&self) -> f64 {
+        self.substep_secs
+    }
+
     pub fn avg_density(&self) -> f64 {
         self.avg_density
     }
+
+    pub fn kinetic_energy(&self) -> f64 {
+        self.particles
+            .iter()
+            .map(|p| 0.5 * PARTICLE_MASS * (p.vel_x().powi(2) + p.vel_y().powi(2)))
+            .sum()
+    }
+}
+
+fn average(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        0.
+    } else {
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+}
+
+/// Handles a particle that's gone past an edge at `edge` along one axis, where the tank spans
+/// `0..len`. `pos` is the particle's position along that axis and `vel` is its velocity along
+/// and across it. Particles going through an open edge are left outside the tank.
+fn cross_edge(
+    boundary: Boundary,
+    pos: &mut f64,
+    (vel, cross_vel): (&mut f64, &mut f64),
+    edge: f64,
+    len: f64,
+    dampening: f64,
+) {
+    match boundary {
+        Boundary::Reflective => {
+            *pos = 2. * edge - *pos;
+            *vel *= -dampening;
+        }
+        Boundary::Periodic => *pos = pos.rem_euclid(len),
+        Boundary::Open => {}
+        Boundary::Sticky => {
+            *pos = edge;
+            *vel = 0.;
+            *cross_vel = 0.;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::seed::add_uniform_points;
+
+    fn run(seed: u64) -> Vec<Particle> {
+        let settings = Settings::default();
+        let mut sim = Simulation::with_seed(40., 30., seed);
+        add_uniform_points(&mut sim, 300, 40., 30.);
+        sim.sync_fish_count(3);
+        sim.drop_body(BodyKind::Boat, 20., 5.);
+        sim.mouse_force.set_positive(20., 15.);
+
+        for step in 0..40 {
+            if step == 20 {
+                sim.sync_particle_count(400);
+            }
+            sim.update(0.005, &settings);
+        }
+        sim.particles
+    }
+
+    #[test]
+    fn same_seed_is_deterministic() {
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7), run(8));
+    }
+
+    #[test]
+    fn faucet_and_sponge() {
+        let settings = Settings::default();
+        let mut sim = Simulation::with_seed(40., 30., 0);
+
+        sim.mouse_force = MouseForce::Faucet { x: 20., y: 5. };
+        sim.update(0.01, &settings);
+        assert_eq!(sim.particles.len(), 6);
+
+        sim.mouse_force = MouseForce::Sponge { x: 20., y: 5. };
+        sim.update(0.01, &settings);
+        assert!(sim.particles.is_empty());
+    }
+
+    #[test]
+    fn boat_floats_on_the_water() {
+        // a pool of water about eight deep, left to settle
+        let settings = Settings::default();
+        let mut sim = Simulation::with_seed(40., 30., 0);
+        for row in 0..12 {
+            for col in 0..50 {
+                sim.add_particle(0.4 + col as f64 * 0.8, 29.6 - row as f64 * 0.8);
+            }
+        }
+        for _ in 0..100 {
+            sim.update(0.01, &settings);
+        }
+
+        sim.drop_body(BodyKind::Boat, 20., 16.);
+        let mut heights = Vec::new();
+        for _ in 0..500 {
+            sim.update(0.01, &settings);
+            heights.push(sim.bodies[0].y());
+        }
+
+        // bobbing to rest, well above where it would sit on the floor
+        let last = &heights[300..];
+        let (low, high) = last.iter().fold((f64::INFINITY, 0f64), |(low, high), y| {
+            (low.min(*y), high.max(*y))
+        });
+        assert!(high < 25.);
+        assert!(high - low < 1.5);
+
+        // with the water pushed out of its way, and down under it
+        let boat = &sim.bodies[0];
+        assert!(
+            sim.particles
+                .iter()
+                .all(|pt| boat.distance((pt.x(), pt.y())).0 >= 0.)
+        );
+        let surface = |near: bool| {
+            sim.particles
+                .iter()
+                .filter(|pt| ((pt.x() - boat.x()).abs() < 4.) == near)
+                .map(|pt| pt.y())
+                .fold(f64::INFINITY, f64::min)
+        };
+        assert!(surface(true) > surface(false) + 0.5);
+    }
+
+    #[test]
+    fn walls_stay_in_the_tank() {
+        let mut sim = Simulation::with_seed(40., 30., 0);
+        sim.paint_wall(39, 14, true);
+        sim.paint_wall(40, 0, true);
+        sim.paint_wall(4_000_000_000, 4_000_000_000, true);
+        assert_eq!(sim.walls.cells().len(), 15);
+        assert_eq!(sim.walls.cells()[14].len(), 40);
+    }
+
+    #[test]
+    fn boundaries() {
+        let mut settings = Settings::default();
+        settings
+            .set_boundaries(boundary::Boundaries {
+                left: Boundary::Periodic,
+                right: Boundary::Periodic,
+                top: Boundary::Sticky,
+                bottom: Boundary::Open,
+            })
+            .unwrap();
+        let mut sim = Simulation::with_seed(40., 30., 0);
+
+        // moving left, and up, so against their velocities
+        sim.particles = vec![
+            Particle::new(0.01, 15., 10., 0.),
+            Particle::new(20., 0.01, 0., 10.),
+            Particle::new(20., 29.99, 0., -10.),
+        ];
+        sim.update(0.01, &settings);
+
+        assert_eq!(sim.particles.len(), 2);
+        assert!(sim.particles[0].x() > 39.);
+        assert_eq!(sim.particles[1].y(), 0.);
+        assert_eq!(sim.particles[1].vel, (0., 0.));
+    }
+
+    #[test]
+    fn bodies_fall_out_of_open_edges() {
+        let mut settings = Settings::default();
+        settings
+            .set_boundaries(boundary::Boundaries {
+                bottom: Boundary::Open,
+                ..Default::default()
+            })
+            .unwrap();
+        let mut sim = Simulation::with_seed(40., 30., 0);
+        sim.drop_body(BodyKind::Ball, 20., 25.);
+
+        for _ in 0..200 {
+            sim.update(0.01, &settings);
+        }
+        assert!(sim.bodies.is_empty());
+    }
+
+    #[test]
+    fn cohesion_pulls_particles_together() {
+        let mut settings = Settings::default();
+        settings.set_by_key("gravity", 0.).unwrap();
+        settings.set_by_key("cohesion", 100.).unwrap();
+        let mut sim = Simulation::with_seed(40., 30., 0);
+        sim.add_particle(19.25, 15.);
+        sim.add_particle(20.75, 15.);
+
+        for _ in 0..10 {
+            sim.update(0.01, &settings);
+        }
+        assert!(sim.particles[1].x() - sim.particles[0].x() < 1.5);
+    }
+
+    #[test]
+    fn ideal_gas_spreads_out() {
+        // how far below the top of the tank the fluid sits on average after a little while
+        let depth = |eos| {
+            let mut settings = Settings::default();
+            settings.set_eos(eos);
+            let mut sim = Simulation::with_seed(30., 20., 0);
+            seed::seed(&mut sim, seed::SeedPattern::Dam, 300);
+            for _ in 0..50 {
+                sim.update(0.01, &settings);
+            }
+            sim.particles.iter().map(|pt| pt.y()).sum::<f64>() / sim.particles.len() as f64
+        };
+        assert!(depth(eos::Eos::IdealGas) < depth(eos::Eos::Linear) - 2.);
+    }
+
+    #[test]
+    fn split_fast_steps() {
+        let settings = Settings::default();
+        let mut sim = Simulation::with_seed(40., 30., 0);
+        sim.add_particle(20., 15.);
+
+        sim.update(0.005, &settings);
+        assert_eq!(sim.substeps(), 1);
+
+        // fast enough to cross the smoothing radius in 0.02s
+        sim.particles[0].vel = (0., -100.);
+        sim.update(0.02, &settings);
+        assert_eq!(sim.substeps(), 3);
+        assert_eq!(sim.substep_secs(), 0.02 / 3.);
+    }
+
+    // two particles side by side with no gravity or viscosity, the first moving right at 10
+    fn pair(key: &str, value: f64) -> (Simulation, Settings) {
+        let mut settings = Settings::default();
+        settings.set_by_key("gravity", 0.).unwrap();
+        settings.set_by_key("viscosity", 0.).unwrap();
+        settings.set_by_key(key, value).unwrap();
+
+        let mut sim = Simulation::with_seed(40., 30., 0);
+        sim.add_particle(19.5, 15.);
+        sim.add_particle(20.5, 15.);
+        // particle velocities point against their motion
+        sim.particles[0].vel = (-10., 0.);
+        (sim, settings)
+    }
+
+    #[test]
+    fn artificial_viscosity_slows_collisions() {
+        let closing_speed = |alpha| {
+            let (mut sim, settings) = pair("artificial-viscosity", alpha);
+            sim.update(0.005, &settings);
+            sim.particles[1].vel_x() - sim.particles[0].vel_x()
+        };
+        assert!(closing_speed(0.5) < closing_speed(0.));
+    }
+
+    #[test]
+    fn xsph_moves_particles_together() {
+        let moved = |strength| {
+            let (mut sim, settings) = pair("xsph", strength);
+            sim.update(0.005, &settings);
+            sim.particles[0].x() - 19.5
+        };
+        assert!(moved(0.5) < moved(0.));
+    }
 }
@@ -0,0 +1,332 @@
+use clap::ValueEnum;
+
+use crate::sim::{
+    boundary::{Boundaries, Boundary},
+    shape::Shape,
+};
+
+/// most bodies in the tank at once; dropping another removes the oldest
+pub const MAX_BODIES: usize = 8;
+
+/// total force and torque on a body
+pub type BodyForce = ((f64, f64), f64);
+
+// how hard the fluid pushes back on a body it's pressed against, on top of its own pressure
+const CONTACT_STIFFNESS: f64 = 200.;
+// slows down bodies spinning freely
+const ANGULAR_DAMPING: f64 = 0.5;
+
+/// A rigid body floating in (or sinking through) the fluid.
+///
+/// Like fish, and unlike particles, a body's velocity points in the direction it's moving.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Body {
+    shape: Shape,
+    pos: (f64, f64),
+    vel: (f64, f64),
+    angle: f64,
+    ang_vel: f64,
+    mass: f64,
+    inertia: f64,
+}
+
+/// The bodies that can be dropped into the tank.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum BodyKind {
+    Boat,
+    Crate,
+    Ball,
+}
+
+impl Body {
+    /// a body of the given shape with uniform `density`, at rest
+    pub fn new(shape: Shape, x: f64, y: f64, density: f64) -> Self {
+        let mass = density * shape.area();
+        let inertia = mass * shape.unit_inertia();
+        Self {
+            shape,
+            pos: (x, y),
+            vel: (0., 0.),
+            angle: 0.,
+            ang_vel: 0.,
+            mass,
+            inertia,
+        }
+    }
+
+    pub fn of_kind(kind: BodyKind, x: f64, y: f64) -> Self {
+        match kind {
+            // a flat hull, wider at the deck than the keel
+            BodyKind::Boat => Self::new(
+                Shape::polygon(vec![(-7., -1.5), (7., -1.5), (4.5, 1.5), (-4.5, 1.5)]),
+                x,
+                y,
+                0.4,
+            ),
+            BodyKind::Crate => Self::new(Shape::rect(5., 5.), x, y, 0.7),
+            BodyKind::Ball => Self::new(Shape::Circle { radius: 2.5 }, x, y, 2.),
+        }
+    }
+
+    /// restores a body exactly as it was saved
+    pub fn from_parts(
+        shape: Shape,
+        pos: (f64, f64),
+        vel: (f64, f64),
+        angle: f64,
+        ang_vel: f64,
+        mass: f64,
+    ) -> Self {
+        let inertia = mass * shape.unit_inertia();
+        Self {
+            shape,
+            pos,
+            vel,
+            angle,
+            ang_vel,
+            mass,
+            inertia,
+        }
+    }
+
+    #[inline]
+    pub fn shape(&self) -> &Shape {
+        &self.shape
+    }
+
+    #[inline]
+    pub fn x(&self) -> f64 {
+        self.pos.0
+    }
+
+    #[inline]
+    pub fn y(&self) -> f64 {
+        self.pos.1
+    }
+
+    #[inline]
+    pub fn vel(&self) -> (f64, f64) {
+        self.vel
+    }
+
+    #[inline]
+    pub fn angle(&self) -> f64 {
+        self.angle
+    }
+
+    #[inline]
+    pub fn ang_vel(&self) -> f64 {
+        self.ang_vel
+    }
+
+    #[inline]
+    pub fn mass(&self) -> f64 {
+        self.mass
+    }
+
+    /// suddenly changes the body's velocity by `impulse`
+    pub fn jolt(&mut self, impulse: (f64, f64)) {
+        self.vel.0 += impulse.0;
+        self.vel.1 += impulse.1;
+    }
+
+    /// whether `p` could be within `margin` of the body, as a cheap check before `distance`
+    pub fn near(&self, p: (f64, f64), margin: f64) -> bool {
+        let reach = self.shape.bounding_radius() + margin;
+        (p.0 - self.pos.0).powi(2) + (p.1 - self.pos.1).powi(2) <= reach * reach
+    }
+
+    /// signed distance from `p` to the body's surface and the outward normal there, in world
+    /// coordinates
+    pub fn distance(&self, p: (f64, f64)) -> (f64, (f64, f64)) {
+        let local = rotate((p.0 - self.pos.0, p.1 - self.pos.1), -self.angle);
+        let (dist, normal) = self.shape.distance(local);
+        (dist, rotate(normal, self.angle))
+    }
+
+    /// velocity of the point of the body at `p`
+    pub fn velocity_at(&self, p: (f64, f64)) -> (f64, f64) {
+        let r = (p.0 - self.pos.0, p.1 - self.pos.1);
+        (
+            self.vel.0 - self.ang_vel * r.1,
+            self.vel.1 + self.ang_vel * r.0,
+        )
+    }
+
+    /// The force the fluid exerts on a particle at `p` touching the body, pushing it out along
+    /// the surface normal and dragging it along with the body. `range` is how close the
+    /// particle has to be to feel it, and `vel` is the particle's velocity in the direction
+    /// it's moving. The body feels the same force in the opposite direction.
+    pub fn contact_force(
+        &self,
+        p: (f64, f64),
+        vel: (f64, f64),
+        pressure: f64,
+        viscosity: f64,
+        range: f64,
+    ) -> Option<(f64, f64)> {
+        let (dist, normal) = self.distance(p);
+        if dist >= range {
+            return None;
+        }
+
+        let overlap = 1. - dist / range;
+        let push = (pressure + CONTACT_STIFFNESS) * overlap;
+        let body_vel = self.velocity_at(p);
+        let drag = viscosity * overlap;
+        Some((
+            push * normal.0 + drag * (body_vel.0 - vel.0),
+            push * normal.1 + drag * (body_vel.1 - vel.1),
+        ))
+    }
+
+    /// Moves the body one step under gravity and the total `force` and `torque` on it from the
+    /// fluid, bouncing off the solid edges of the tank and wrapping around the periodic ones.
+    /// `restitution` is the fraction of speed kept in a bounce.
+    pub fn update(
+        &mut self,
+        (force, torque): BodyForce,
+        gravity: (f64, f64),
+        restitution: f64,
+        (width, height): (f64, f64),
+        boundaries: Boundaries,
+        dt_secs: f64,
+    ) {
+        self.vel.0 += (force.0 / self.mass + gravity.0) * dt_secs;
+        self.vel.1 += (force.1 / self.mass + gravity.1) * dt_secs;
+        self.ang_vel += torque / self.inertia * dt_secs;
+        self.ang_vel *= 1. - ANGULAR_DAMPING * dt_secs;
+
+        self.pos.0 += self.vel.0 * dt_secs;
+        self.pos.1 += self.vel.1 * dt_secs;
+        self.angle += self.ang_vel * dt_secs;
+
+        // edges as (boundary, a point on them, normal pointing into the tank)
+        let edges = [
+            (boundaries.left, (0., 0.), (1., 0.)),
+            (boundaries.right, (width, 0.), (-1., 0.)),
+            (boundaries.top, (0., 0.), (0., 1.)),
+            (boundaries.bottom, (0., height), (0., -1.)),
+        ];
+        for (boundary, origin, normal) in edges {
+            match boundary {
+                Boundary::Reflective => self.collide_with_wall(origin, normal, restitution),
+                Boundary::Sticky => self.collide_with_wall(origin, normal, 0.),
+                // bodies that leave through an open edge are removed by the simulation
+                Boundary::Periodic | Boundary::Open => {}
+            }
+        }
+
+        // wrapped once the center crosses, like a particle would be
+        let (wrap_x, wrap_y) = boundaries.periodic();
+        if wrap_x {
+            self.pos.0 = self.pos.0.rem_euclid(width);
+        }
+        if wrap_y {
+            self.pos.1 = self.pos.1.rem_euclid(height);
+        }
+    }
+
+    // points of the body that can touch a flat wall
+    fn extremes(&self) -> Vec<(f64, f64)> {
+        match &self.shape {
+            Shape::Circle { radius } => [(1., 0.), (-1., 0.), (0., 1.), (0., -1.)]
+                .iter()
+                .map(|d| (self.pos.0 + radius * d.0, self.pos.1 + radius * d.1))
+                .collect(),
+            Shape::Polygon { vertices } => vertices
+                .iter()
+                .map(|v| {
+                    let v = rotate(*v, self.angle);
+                    (self.pos.0 + v.0, self.pos.1 + v.1)
+                })
+                .collect(),
+        }
+    }
+
+    fn collide_with_wall(&mut self, origin: (f64, f64), normal: (f64, f64), restitution: f64) {
+        // the point that's gone furthest through the wall
+        let Some((depth, point)) = self
+            .extremes()
+            .into_iter()
+            .map(|p| ((origin.0 - p.0) * normal.0 + (origin.1 - p.1) * normal.1, p))
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+        else {
+            return;
+        };
+        if depth <= 0. {
+            return;
+        }
+
+        self.pos.0 += depth * normal.0;
+        self.pos.1 += depth * normal.1;
+
+        let vel = self.velocity_at(point);
+        let normal_vel = vel.0 * normal.0 + vel.1 * normal.1;
+        if normal_vel >= 0. {
+            return;
+        }
+
+        // impulse at the contact point that reverses its velocity into the wall
+        let r = (point.0 - self.pos.0, point.1 - self.pos.1);
+        let r_cross_n = r.0 * normal.1 - r.1 * normal.0;
+        let impulse = -(1. + restitution) * normal_vel
+            / (1. / self.mass + r_cross_n * r_cross_n / self.inertia);
+        self.vel.0 += impulse * normal.0 / self.mass;
+        self.vel.1 += impulse * normal.1 / self.mass;
+        self.ang_vel += impulse * r_cross_n / self.inertia;
+    }
+}
+
+/// rotates `v` by `angle` radians (clockwise on screen, since y points down)
+pub fn rotate(v: (f64, f64), angle: f64) -> (f64, f64) {
+    let (sin, cos) = angle.sin_cos();
+    (v.0 * cos - v.1 * sin, v.0 * sin + v.1 * cos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fall_and_land_on_the_floor() {
+        let mut body = Body::of_kind(BodyKind::Crate, 10., 5.);
+        let boundaries = Boundaries::default();
+        for _ in 0..1000 {
+            body.update(((0., 0.), 0.), (0., 15.), 0.1, (20., 20.), boundaries, 0.01);
+        }
+
+        // resting on the bottom, half its height above it
+        assert!((body.y() - 17.5).abs() < 0.1);
+        assert!(body.vel().1.abs() < 0.5);
+    }
+
+    #[test]
+    fn wrap_around_periodic_edges() {
+        let boundaries = Boundaries {
+            left: Boundary::Periodic,
+            right: Boundary::Periodic,
+            ..Default::default()
+        };
+        let mut body = Body::of_kind(BodyKind::Crate, 18., 17.5);
+        body.vel = (100., 0.);
+        body.update(((0., 0.), 0.), (0., 0.), 0.1, (20., 20.), boundaries, 0.05);
+
+        // through the right edge and back in on the left, still going
+        assert!((body.x() - 3.).abs() < 1e-9);
+        assert!(body.vel().0 > 99.);
+    }
+
+    #[test]
+    fn push_particles_out() {
+        let body = Body::of_kind(BodyKind::Crate, 10., 10.);
+        let force = body
+            .contact_force((10., 13.), (0., 0.), 0., 0., 2.)
+            .unwrap();
+        assert!(force.1 > 0.);
+        assert!(
+            body.contact_force((10., 16.), (0., 0.), 0., 0., 2.)
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use anyhow::ensure;
+use clap::ValueEnum;
+
+/// What happens to particles that reach an edge of the tank.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Boundary {
+    /// bounce off, losing some speed to dampening
+    #[default]
+    Reflective,
+    /// wrap around to the opposite edge
+    Periodic,
+    /// leave the tank and disappear
+    Open,
+    /// stop dead against the edge (no-slip)
+    Sticky,
+}
+
+/// The boundary on each edge of the tank.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Boundaries {
+    pub left: Boundary,
+    pub right: Boundary,
+    pub top: Boundary,
+    pub bottom: Boundary,
+}
+
+impl Boundaries {
+    pub fn all(boundary: Boundary) -> Self {
+        Self {
+            left: boundary,
+            right: boundary,
+            top: boundary,
+            bottom: boundary,
+        }
+    }
+
+    /// whether particles wrap around horizontally and vertically
+    pub fn periodic(&self) -> (bool, bool) {
+        (
+            self.left == Boundary::Periodic,
+            self.top == Boundary::Periodic,
+        )
+    }
+
+    /// checks that periodic edges come in opposite pairs, since a particle wrapping around one
+    /// edge comes back in through the other
+    pub fn check(&self) -> anyhow::Result<()> {
+        ensure!(
+            (self.left == Boundary::Periodic) == (self.right == Boundary::Periodic),
+            "left and right edges must both be periodic or neither"
+        );
+        ensure!(
+            (self.top == Boundary::Periodic) == (self.bottom == Boundary::Periodic),
+            "top and bottom edges must both be periodic or neither"
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn periodic_edges_come_in_pairs() {
+        let mut boundaries = Boundaries {
+            left: Boundary::Periodic,
+            ..Default::default()
+        };
+        assert!(boundaries.check().is_err());
+
+        boundaries.right = Boundary::Periodic;
+        boundaries.top = Boundary::Open;
+        boundaries.check().unwrap();
+        assert_eq!(boundaries.periodic(), (true, false));
+    }
+}
@@ -1,3 +1,36 @@
-pub const TIMESTEP_MS: u64 = 5;
+pub const TIMESTEP_MS: u64 = 10;
+pub const SUBSTEPS: usize = 2;
+pub const MAX_CATCH_UP_STEPS: usize = 5;
+
+// each update is split so that no particle moves more than this fraction of the smoothing
+// radius, or speeds up too fast for this fraction of it, in one substep, up to a limit
+pub const CFL_SPEED: f64 = 0.4;
+pub const CFL_FORCE: f64 = 0.25;
+pub const MAX_ADAPTIVE_SUBSTEPS: usize = 8;
+
+// how much history is kept for rewinding, and how often it's sampled
+pub const HISTORY_SECS: u64 = 10;
+pub const HISTORY_INTERVAL_MS: u64 = 100;
 
 pub const PARTICLE_MASS: f64 = 1.;
+// strength of near-pressure relative to stiffness, for the double-density pressure model
+pub const NEAR_STIFFNESS: f64 = 0.1;
+// strength of the correction against clumping where pressure is negative, and the spacing
+// between particles (as a fraction of the smoothing radius) it's measured against
+pub const TENSILE_CORRECTION: f64 = 0.2;
+pub const TENSILE_SPACING: f64 = 0.5;
+
+// the position based solver takes this many passes at the density each step, softened by the
+// relaxation, with an artificial pressure this strong to keep the surface from clumping
+pub const PBF_ITERATIONS: usize = 4;
+pub const PBF_RELAXATION: f64 = 10.;
+pub const PBF_CORRECTION: f64 = 0.001;
+
+// the faucet pours this many particles a second, spread around the cursor, at this speed
+pub const FAUCET_RATE: f64 = 600.;
+pub const FAUCET_RADIUS: f64 = 1.;
+pub const FAUCET_SPEED: f64 = 10.;
+pub const SPONGE_RADIUS: f64 = 4.;
+
+// how fast a shake throws everything in the tank
+pub const SHAKE_SPEED: f64 = 20.;
@@ -0,0 +1,63 @@
+use clap::ValueEnum;
+
+/// Equation of state, turning the density of the fluid into pressure.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Eos {
+    /// pressure in proportion to how far the density is from the target
+    #[default]
+    Linear,
+    /// the Tait equation for weakly compressible liquids, stiffening sharply as the density
+    /// rises past the target
+    Tait,
+    /// pressure in proportion to the density itself, so the fluid spreads out like a gas
+    IdealGas,
+}
+
+impl Eos {
+    /// name shown in the info panel
+    pub fn label(self) -> &'static str {
+        match self {
+            Eos::Linear => "Linear",
+            Eos::Tait => "Tait",
+            Eos::IdealGas => "Ideal gas",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Eos::Linear => Eos::Tait,
+            Eos::Tait => Eos::IdealGas,
+            Eos::IdealGas => Eos::Linear,
+        }
+    }
+
+    /// pressure at `density`, negative where the fluid would pull together. Stiffness is scaled
+    /// so that every equation pushes back equally hard on small squeezes around the target.
+    pub fn pressure(self, density: f64, target_density: f64, stiffness: f64, gamma: f64) -> f64 {
+        match self {
+            Eos::Linear => stiffness * (density - target_density),
+            Eos::Tait => {
+                stiffness * target_density / gamma * ((density / target_density).powf(gamma) - 1.)
+            }
+            Eos::IdealGas => stiffness * density,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tait_matches_linear_near_target() {
+        let linear = Eos::Linear.pressure(1.01, 1., 100., 7.);
+        let tait = Eos::Tait.pressure(1.01, 1., 100., 7.);
+        assert!((tait - linear).abs() < 0.05 * linear);
+
+        // but pushes back much harder when squeezed
+        assert!(
+            Eos::Tait.pressure(1.5, 1., 100., 7.) > 3. * Eos::Linear.pressure(1.5, 1., 100., 7.)
+        );
+        assert_eq!(Eos::Tait.pressure(1., 1., 100., 7.), 0.);
+    }
+}
@@ -0,0 +1,233 @@
+// how far a fish can see its neighbors, and how close it lets them get
+const PERCEPTION_RADIUS: f64 = 12.;
+const SEPARATION_RADIUS: f64 = 4.;
+
+// weights of the three boids rules
+const SEPARATION_WEIGHT: f64 = 8.;
+const ALIGNMENT_WEIGHT: f64 = 1.;
+const COHESION_WEIGHT: f64 = 0.4;
+
+// fish steer away from walls closer than this
+const WALL_MARGIN: f64 = 6.;
+const WALL_WEIGHT: f64 = 10.;
+
+const CRUISE_SPEED: f64 = 8.;
+const MAX_SPEED: f64 = 20.;
+// how strongly fish speed up or slow down towards their cruising speed
+const THRUST: f64 = 2.;
+// how quickly a fish is carried along with the water around it
+const FLUID_DRAG: f64 = 3.;
+
+/// radius around a fish (per unit of size) that particles are pushed out of
+pub const FISH_RADIUS: f64 = 1.5;
+/// strength of the push fish give to nearby particles
+pub const FISH_PUSH: f64 = 20.;
+
+pub const MIN_SIZE: f64 = 1.;
+pub const MAX_SIZE: f64 = 2.;
+
+/// A fish swimming around the tank, following boids rules and carried along by the fluid.
+///
+/// Unlike particles, a fish's velocity points in the direction it's moving.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fish {
+    pos: (f64, f64),
+    vel: (f64, f64),
+    heading: f64,
+    size: f64,
+}
+
+impl Fish {
+    pub fn new(x: f64, y: f64, heading: f64, size: f64) -> Self {
+        Self {
+            pos: (x, y),
+            vel: (CRUISE_SPEED * heading.cos(), CRUISE_SPEED * heading.sin()),
+            heading,
+            size,
+        }
+    }
+
+    pub fn with_vel(mut self, vel: (f64, f64)) -> Self {
+        self.vel = vel;
+        self
+    }
+
+    #[inline]
+    pub fn x(&self) -> f64 {
+        self.pos.0
+    }
+
+    #[inline]
+    pub fn y(&self) -> f64 {
+        self.pos.1
+    }
+
+    #[inline]
+    pub fn vel(&self) -> (f64, f64) {
+        self.vel
+    }
+
+    /// direction the fish is facing, in radians from the positive x axis
+    #[inline]
+    pub fn heading(&self) -> f64 {
+        self.heading
+    }
+
+    #[inline]
+    pub fn size(&self) -> f64 {
+        self.size
+    }
+
+    #[inline]
+    pub fn facing_right(&self) -> bool {
+        self.heading.cos() >= 0.
+    }
+
+    pub fn radius(&self) -> f64 {
+        FISH_RADIUS * self.size
+    }
+}
+
+/// Moves every fish one step. `fluid_vels` holds the velocity of the water around each fish,
+/// or `None` if it's out of the water, in which case it falls under `gravity`.
+pub fn update_school(
+    school: &mut [Fish],
+    fluid_vels: &[Option<(f64, f64)>],
+    (width, height): (f64, f64),
+    gravity: (f64, f64),
+    dt_secs: f64,
+) {
+    let steering = school
+        .iter()
+        .map(|fish| {
+            let boids = boids_steering(fish, school);
+            let wall = wall_steering(fish, width, height);
+            (boids.0 + wall.0, boids.1 + wall.1)
+        })
+        .collect::<Vec<_>>();
+
+    for ((fish, steer), fluid_vel) in school.iter_mut().zip(steering).zip(fluid_vels) {
+        let mut acc = steer;
+
+        match fluid_vel {
+            Some(fluid_vel) => {
+                // swim towards cruising speed, while being carried along by the water
+                let speed = fish.vel.0.hypot(fish.vel.1).max(1e-6);
+                let thrust = THRUST * (CRUISE_SPEED - speed) / speed;
+                acc.0 += thrust * fish.vel.0 + FLUID_DRAG * (fluid_vel.0 - fish.vel.0);
+                acc.1 += thrust * fish.vel.1 + FLUID_DRAG * (fluid_vel.1 - fish.vel.1);
+            }
+            // a fish out of water can't swim, it just falls
+            None => acc = gravity,
+        }
+
+        fish.vel.0 += acc.0 * dt_secs;
+        fish.vel.1 += acc.1 * dt_secs;
+
+        let speed = fish.vel.0.hypot(fish.vel.1);
+        if speed > MAX_SPEED {
+            fish.vel.0 *= MAX_SPEED / speed;
+            fish.vel.1 *= MAX_SPEED / speed;
+        }
+        if speed > 1e-3 {
+            fish.heading = fish.vel.1.atan2(fish.vel.0);
+        }
+
+        fish.pos.0 += fish.vel.0 * dt_secs;
+        fish.pos.1 += fish.vel.1 * dt_secs;
+
+        // bounce off the edges of the tank
+        if fish.pos.0 < 0. || fish.pos.0 > width {
+            fish.pos.0 = fish.pos.0.clamp(0., width);
+            fish.vel.0 = -fish.vel.0;
+        }
+        if fish.pos.1 < 0. || fish.pos.1 > height {
+            fish.pos.1 = fish.pos.1.clamp(0., height);
+            fish.vel.1 = -fish.vel.1;
+        }
+    }
+}
+
+// separation, alignment and cohesion with the other fish nearby
+fn boids_steering(fish: &Fish, school: &[Fish]) -> (f64, f64) {
+    let mut separation = (0., 0.);
+    let mut avg_vel = (0., 0.);
+    let mut center = (0., 0.);
+    let mut neighbors = 0;
+
+    for other in school {
+        let disp = (fish.pos.0 - other.pos.0, fish.pos.1 - other.pos.1);
+        let dist = disp.0.hypot(disp.1);
+        if dist <= 0. || dist > PERCEPTION_RADIUS {
+            continue;
+        }
+
+        if dist < SEPARATION_RADIUS {
+            let push = (SEPARATION_RADIUS - dist) / dist;
+            separation.0 += push * disp.0;
+            separation.1 += push * disp.1;
+        }
+        avg_vel.0 += other.vel.0;
+        avg_vel.1 += other.vel.1;
+        center.0 += other.pos.0;
+        center.1 += other.pos.1;
+        neighbors += 1;
+    }
+
+    if neighbors == 0 {
+        return (0., 0.);
+    }
+
+    let n = neighbors as f64;
+    (
+        SEPARATION_WEIGHT * separation.0
+            + ALIGNMENT_WEIGHT * (avg_vel.0 / n - fish.vel.0)
+            + COHESION_WEIGHT * (center.0 / n - fish.pos.0),
+        SEPARATION_WEIGHT * separation.1
+            + ALIGNMENT_WEIGHT * (avg_vel.1 / n - fish.vel.1)
+            + COHESION_WEIGHT * (center.1 / n - fish.pos.1),
+    )
+}
+
+// turn away from any wall that's getting close
+fn wall_steering(fish: &Fish, width: f64, height: f64) -> (f64, f64) {
+    let push = |dist: f64| WALL_WEIGHT * (WALL_MARGIN - dist).max(0.);
+    (
+        push(fish.pos.0) - push(width - fish.pos.0),
+        push(fish.pos.1) - push(height - fish.pos.1),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fall_when_out_of_water() {
+        let mut school = [Fish::new(10., 10., 0., 1.)];
+        update_school(&mut school, &[None], (40., 40.), (0., 15.), 0.1);
+
+        assert!(school[0].y() > 10.);
+    }
+
+    #[test]
+    fn carried_by_the_water() {
+        let mut school = [Fish::new(20., 20., 0., 1.)];
+        for _ in 0..100 {
+            update_school(&mut school, &[Some((0., -10.))], (40., 400.), (0., 15.), 0.01);
+        }
+
+        assert!(school[0].y() < 20.);
+        assert!(!school[0].facing_right() || school[0].heading() < 0.);
+    }
+
+    #[test]
+    fn keep_apart() {
+        let mut school = [Fish::new(20., 20., 0., 1.), Fish::new(21., 20., 0., 1.)];
+        for _ in 0..20 {
+            update_school(&mut school, &[Some((0., 0.)); 2], (40., 40.), (0., 15.), 0.01);
+        }
+
+        assert!(school[1].x() - school[0].x() > 1.);
+    }
+}
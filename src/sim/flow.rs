@@ -0,0 +1,102 @@
+use rand::Rng;
+
+/// Where an emitter's particles come from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Source {
+    Point((f64, f64)),
+    /// spread evenly along the segment between two points
+    Segment((f64, f64), (f64, f64)),
+}
+
+/// Pours a steady stream of particles into the tank.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Emitter {
+    source: Source,
+    // particles per second
+    rate: f64,
+    // radians from the positive x axis, so positive angles point down
+    direction: f64,
+    speed: f64,
+    // fraction of a particle left over from the last step
+    carry: f64,
+}
+
+impl Emitter {
+    pub fn new(source: Source, rate: f64, direction: f64, speed: f64) -> Self {
+        Self {
+            source,
+            rate,
+            direction,
+            speed,
+            carry: 0.,
+        }
+    }
+
+    /// how many particles to emit over the next `dt_secs`
+    pub fn due(&mut self, dt_secs: f64) -> usize {
+        self.carry += self.rate * dt_secs;
+        let count = self.carry as usize;
+        self.carry = self.carry.fract();
+        count
+    }
+
+    /// a random point on the source, nudged slightly so that particles don't start on top of
+    /// each other
+    pub fn spawn_point(&self, rng: &mut impl Rng) -> (f64, f64) {
+        let (x, y) = match self.source {
+            Source::Point(p) => p,
+            Source::Segment(a, b) => {
+                let t = rng.random::<f64>();
+                (a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1))
+            }
+        };
+        let jitter = (rng.random::<f64>() - 0.5, rng.random::<f64>() - 0.5);
+        (x + 0.5 * jitter.0, y + 0.5 * jitter.1)
+    }
+
+    /// velocity of new particles, in the direction they move
+    pub fn velocity(&self) -> (f64, f64) {
+        let (sin, cos) = self.direction.sin_cos();
+        (self.speed * cos, self.speed * sin)
+    }
+}
+
+/// A rectangular region that removes any particle entering it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Drain {
+    min: (f64, f64),
+    max: (f64, f64),
+}
+
+impl Drain {
+    /// a drain between two opposite corners
+    pub fn new(a: (f64, f64), b: (f64, f64)) -> Self {
+        Self {
+            min: (a.0.min(b.0), a.1.min(b.1)),
+            max: (a.0.max(b.0), a.1.max(b.1)),
+        }
+    }
+
+    pub fn contains(&self, (x, y): (f64, f64)) -> bool {
+        (self.min.0..=self.max.0).contains(&x) && (self.min.1..=self.max.1).contains(&y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emit_at_rate() {
+        let mut emitter = Emitter::new(Source::Point((0., 0.)), 250., 0., 1.);
+        let total = (0..100).map(|_| emitter.due(0.01)).sum::<usize>();
+        assert!((249..=250).contains(&total));
+    }
+
+    #[test]
+    fn drain_from_any_corners() {
+        let drain = Drain::new((10., 8.), (2., 4.));
+        assert!(drain.contains((5., 5.)));
+        assert!(!drain.contains((5., 9.)));
+    }
+}
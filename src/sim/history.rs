@@ -0,0 +1,129 @@
+use std::collections::VecDeque;
+
+use crate::sim::snapshot::Snapshot;
+
+/// A ring buffer of recent simulation states that can be scrubbed back and forth through.
+pub struct History {
+    frames: VecDeque<Snapshot>,
+    capacity: usize,
+    // index of the frame being shown, or `None` when following the live sim
+    cursor: Option<usize>,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            frames: VecDeque::with_capacity(capacity),
+            capacity,
+            cursor: None,
+        }
+    }
+
+    /// adds a frame, dropping the oldest if the buffer is full
+    pub fn push(&mut self, frame: Snapshot) {
+        self.resume();
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    /// Moves `delta` frames backward (negative) or forward through the history, returning the
+    /// frame to show, if it changed. `live` is the current state, which is kept as the newest
+    /// frame when first stepping back so that it can be returned to.
+    pub fn scrub(&mut self, delta: isize, live: impl FnOnce() -> Snapshot) -> Option<&Snapshot> {
+        let cursor = match self.cursor {
+            Some(cursor) => cursor,
+            None if delta < 0 => {
+                self.push(live());
+                self.frames.len() - 1
+            }
+            None => return None,
+        };
+
+        let new_cursor = cursor
+            .saturating_add_signed(delta)
+            .min(self.frames.len() - 1);
+        self.cursor = Some(new_cursor);
+        (new_cursor != cursor).then(|| &self.frames[new_cursor])
+    }
+
+    /// goes back to following the live sim from the frame being shown, forgetting any frames
+    /// after it
+    pub fn resume(&mut self) {
+        if let Some(cursor) = self.cursor.take() {
+            self.frames.truncate(cursor + 1);
+        }
+    }
+
+    /// how many frames behind the newest one the cursor is
+    pub fn frames_back(&self) -> usize {
+        self.cursor
+            .map_or(0, |cursor| self.frames.len() - 1 - cursor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{MouseForce, particle::Particle};
+
+    // frames are told apart by their width
+    fn frame(width: f64) -> Snapshot {
+        Snapshot {
+            width,
+            height: 1.,
+            mouse_force: MouseForce::None,
+            particles: vec![Particle::new(0., 0., 0., 0.)],
+            fish: Vec::new(),
+            bodies: Vec::new(),
+            walls: Default::default(),
+        }
+    }
+
+    fn scrub(history: &mut History, delta: isize) -> Option<f64> {
+        history.scrub(delta, || frame(99.)).map(|f| f.width)
+    }
+
+    #[test]
+    fn drop_oldest_frames() {
+        let mut history = History::new(3);
+        for width in 0..5 {
+            history.push(frame(width as f64));
+        }
+
+        assert_eq!(history.frames.len(), 3);
+        assert_eq!(history.frames[0].width, 2.);
+    }
+
+    #[test]
+    fn scrub_back_and_forward() {
+        let mut history = History::new(10);
+        for width in 0..3 {
+            history.push(frame(width as f64));
+        }
+
+        assert_eq!(scrub(&mut history, 1), None);
+        assert_eq!(scrub(&mut history, -1), Some(2.));
+        assert_eq!(scrub(&mut history, -5), Some(0.));
+        assert_eq!(history.frames_back(), 3);
+        assert_eq!(scrub(&mut history, -1), None);
+        assert_eq!(scrub(&mut history, 5), Some(99.));
+        assert_eq!(history.frames_back(), 0);
+    }
+
+    #[test]
+    fn resume_forgets_the_future() {
+        let mut history = History::new(10);
+        for width in 0..3 {
+            history.push(frame(width as f64));
+        }
+
+        scrub(&mut history, -2);
+        history.resume();
+        history.push(frame(5.));
+
+        let widths = history.frames.iter().map(|f| f.width).collect::<Vec<_>>();
+        assert_eq!(widths, [0., 1., 5.]);
+    }
+}
@@ -0,0 +1,213 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{Context, anyhow, bail};
+
+use crate::sim::{
+    MouseForce, Simulation, body::BodyKind, eos::Eos, integrator::Integrator,
+    pressure::PressureModel,
+    settings::{Settings, choice_name, parse_choice},
+    solver::Solver,
+};
+
+/// A change to the simulation coming from the user. Inputs are queued with
+/// [`Simulation::push_input`] and applied between steps, so they can be recorded against the
+/// step they took effect on and replayed exactly.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Input {
+    Mouse(MouseForce),
+    Param { idx: usize, value: f64 },
+    Resize { width: f64, height: f64 },
+    Drop { kind: BodyKind, x: f64, y: f64 },
+    /// fills in (or erases) a cell of the painted walls
+    Wall { col: usize, row: usize, solid: bool },
+    /// jolts the whole tank
+    Shake,
+    Pressure(PressureModel),
+    Eos(Eos),
+    NegativePressure(bool),
+    Solver(Solver),
+    Integrator(Integrator),
+}
+
+impl Input {
+    pub fn apply(&self, sim: &mut Simulation, settings: &mut Settings) {
+        match *self {
+            Input::Mouse(force) => sim.mouse_force = force,
+            Input::Param { idx, value } => {
+                // values are checked when they're created or parsed
+                let _ = settings.set_value(idx, value);
+                if idx == Settings::PARTICLE_COUNT_IDX {
+                    sim.sync_particle_count(settings.particle_count());
+                }
+                if idx == Settings::FISH_COUNT_IDX {
+                    sim.sync_fish_count(settings.fish_count());
+                }
+            }
+            Input::Resize { width, height } => sim.resize(width, height),
+            Input::Drop { kind, x, y } => sim.drop_body(kind, x, y),
+            Input::Wall { col, row, solid } => sim.paint_wall(col, row, solid),
+            Input::Shake => sim.shake(),
+            Input::Pressure(model) => settings.set_pressure_model(model),
+            Input::Eos(eos) => settings.set_eos(eos),
+            Input::NegativePressure(on) => settings.set_negative_pressure(on),
+            Input::Solver(solver) => settings.set_solver(solver),
+            Input::Integrator(integrator) => settings.set_integrator(integrator),
+        }
+    }
+}
+
+// inputs are written one per line in recordings, e.g. `mouse positive 10.5 20`
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Input::Mouse(MouseForce::None) => write!(f, "mouse none"),
+            Input::Mouse(MouseForce::Positive { x, y }) => write!(f, "mouse positive {} {}", x, y),
+            Input::Mouse(MouseForce::Negative { x, y }) => write!(f, "mouse negative {} {}", x, y),
+            Input::Mouse(MouseForce::Faucet { x, y }) => write!(f, "mouse faucet {} {}", x, y),
+            Input::Mouse(MouseForce::Sponge { x, y }) => write!(f, "mouse sponge {} {}", x, y),
+            Input::Param { idx, value } => write!(f, "param {} {}", Settings::KEYS[*idx], value),
+            Input::Resize { width, height } => write!(f, "resize {} {}", width, height),
+            Input::Drop { kind, x, y } => write!(f, "drop {} {} {}", choice_name(kind), x, y),
+            Input::Wall { col, row, solid } => {
+                let verb = if *solid { "wall" } else { "erase" };
+                write!(f, "{} {} {}", verb, col, row)
+            }
+            Input::Shake => write!(f, "shake"),
+            Input::Pressure(model) => write!(f, "pressure {}", choice_name(model)),
+            Input::Eos(eos) => write!(f, "eos {}", choice_name(eos)),
+            Input::NegativePressure(on) => {
+                write!(f, "negative-pressure {}", if *on { "on" } else { "off" })
+            }
+            Input::Solver(solver) => write!(f, "solver {}", choice_name(solver)),
+            Input::Integrator(integrator) => {
+                write!(f, "integrator {}", choice_name(integrator))
+            }
+        }
+    }
+}
+
+impl FromStr for Input {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words = s.split_whitespace().collect::<Vec<_>>();
+        let num = |idx: usize| -> anyhow::Result<f64> {
+            let word = words.get(idx).ok_or_else(|| anyhow!("missing value"))?;
+            word.parse()
+                .with_context(|| format!("`{}` is not a number", word))
+        };
+
+        let input = match words.as_slice() {
+            ["mouse", "none"] => Input::Mouse(MouseForce::None),
+            ["mouse", "positive", _, _] => Input::Mouse(MouseForce::Positive {
+                x: num(2)?,
+                y: num(3)?,
+            }),
+            ["mouse", "negative", _, _] => Input::Mouse(MouseForce::Negative {
+                x: num(2)?,
+                y: num(3)?,
+            }),
+            ["param", key, _] => {
+                let value = num(2)?;
+                let idx = Settings::KEYS
+                    .iter()
+                    .position(|k| k == key)
+                    .ok_or_else(|| anyhow!("unknown setting `{}`", key))?;
+                Settings::default().set_value(idx, value)?;
+                Input::Param { idx, value }
+            }
+            ["mouse", "faucet", _, _] => Input::Mouse(MouseForce::Faucet {
+                x: num(2)?,
+                y: num(3)?,
+            }),
+            ["mouse", "sponge", _, _] => Input::Mouse(MouseForce::Sponge {
+                x: num(2)?,
+                y: num(3)?,
+            }),
+            ["resize", _, _] => Input::Resize {
+                width: num(1)?,
+                height: num(2)?,
+            },
+            ["drop", kind, _, _] => Input::Drop {
+                kind: parse_choice(kind)?,
+                x: num(2)?,
+                y: num(3)?,
+            },
+            [verb @ ("wall" | "erase"), col, row] => Input::Wall {
+                col: col
+                    .parse()
+                    .with_context(|| format!("`{}` is not a column", col))?,
+                row: row
+                    .parse()
+                    .with_context(|| format!("`{}` is not a row", row))?,
+                solid: *verb == "wall",
+            },
+            ["shake"] => Input::Shake,
+            ["pressure", model] => Input::Pressure(parse_choice(model)?),
+            ["eos", eos] => Input::Eos(parse_choice(eos)?),
+            ["negative-pressure", "on"] => Input::NegativePressure(true),
+            ["negative-pressure", "off"] => Input::NegativePressure(false),
+            ["solver", solver] => Input::Solver(parse_choice(solver)?),
+            ["integrator", integrator] => Input::Integrator(parse_choice(integrator)?),
+            _ => bail!("unrecognized input `{}`", s),
+        };
+        Ok(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let inputs = [
+            Input::Mouse(MouseForce::None),
+            Input::Mouse(MouseForce::Positive { x: 1.5, y: 2. }),
+            Input::Mouse(MouseForce::Negative { x: 0.1, y: 30. }),
+            Input::Mouse(MouseForce::Faucet { x: 5., y: 6. }),
+            Input::Mouse(MouseForce::Sponge { x: 7., y: 8. }),
+            Input::Param { idx: 3, value: 7.3 },
+            Input::Resize {
+                width: 120.,
+                height: 64.,
+            },
+            Input::Drop {
+                kind: BodyKind::Boat,
+                x: 40.,
+                y: 3.5,
+            },
+            Input::Wall {
+                col: 4,
+                row: 7,
+                solid: true,
+            },
+            Input::Wall {
+                col: 4,
+                row: 7,
+                solid: false,
+            },
+            Input::Shake,
+            Input::Pressure(PressureModel::DoubleDensity),
+            Input::Eos(Eos::Tait),
+            Input::NegativePressure(true),
+            Input::Solver(Solver::Pbf),
+            Input::Integrator(Integrator::VelocityVerlet),
+        ];
+
+        for input in inputs {
+            assert_eq!(input.to_string().parse::<Input>().unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn reject_bad_inputs() {
+        assert!("mouse sideways 1 2".parse::<Input>().is_err());
+        assert!("param gravity 500".parse::<Input>().is_err());
+        assert!("resize wide 10".parse::<Input>().is_err());
+        assert!("drop anchor 1 2".parse::<Input>().is_err());
+        assert!("wall -1 2".parse::<Input>().is_err());
+        assert!("pressure high".parse::<Input>().is_err());
+        assert!("negative-pressure maybe".parse::<Input>().is_err());
+    }
+}
@@ -0,0 +1,57 @@
+use clap::ValueEnum;
+
+/// How particles are moved by the forces on them each step.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Integrator {
+    /// speed up, then move at the new speed (semi-implicit Euler)
+    #[default]
+    Euler,
+    /// velocities live half a step ahead of positions, kicked by the average of the last two
+    /// step lengths so that changing substeps doesn't add energy
+    Leapfrog,
+    /// velocities kept in step with positions, averaging the acceleration at both ends of
+    /// each step
+    VelocityVerlet,
+}
+
+impl Integrator {
+    /// name shown in the info panel
+    pub fn label(self) -> &'static str {
+        match self {
+            Integrator::Euler => "Euler",
+            Integrator::Leapfrog => "Leapfrog",
+            Integrator::VelocityVerlet => "Verlet",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Integrator::Euler => Integrator::Leapfrog,
+            Integrator::Leapfrog => Integrator::VelocityVerlet,
+            Integrator::VelocityVerlet => Integrator::Euler,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::particle::Particle;
+
+    // how far a particle falls from rest in `steps` steps under constant acceleration
+    fn fall(integrator: Integrator, steps: usize, dt_secs: f64) -> f64 {
+        let mut particle = Particle::new(0., 0., 0., 0.);
+        for _ in 0..steps {
+            particle.integrate(integrator, (0., -10.), dt_secs);
+        }
+        particle.y()
+    }
+
+    #[test]
+    fn symplectic_integrators_fall_exactly() {
+        // 5 * 1^2, which Euler overshoots by half a step's worth
+        assert!((fall(Integrator::Euler, 10, 0.1) - 5.5).abs() < 1e-9);
+        assert!((fall(Integrator::Leapfrog, 10, 0.1) - 5.).abs() < 1e-9);
+        assert!((fall(Integrator::VelocityVerlet, 10, 0.1) - 5.).abs() < 1e-9);
+    }
+}
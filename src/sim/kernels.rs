@@ -14,3 +14,26 @@ pub fn visc_laplacian(dist: f64, smoothing_radius: f64) -> f64 {
     let coeff = 45. / (PI * smoothing_radius.powi(6));
     coeff * (smoothing_radius - dist)
 }
+
+/// Cohesion kernel from Akinci et al. 2013, attracting particles more than half a smoothing
+/// radius apart and gently repelling closer ones.
+pub fn cohesion(dist: f64, smoothing_radius: f64) -> f64 {
+    let coeff = 32. / (PI * smoothing_radius.powi(9));
+    let spline = (smoothing_radius - dist).powi(3) * dist.powi(3);
+    if 2. * dist > smoothing_radius {
+        coeff * spline
+    } else {
+        coeff * (2. * spline - smoothing_radius.powi(6) / 64.)
+    }
+}
+
+/// Sharper kernel for near-density, weighting the closest neighbors most heavily.
+pub fn near(dist: f64, smoothing_radius: f64) -> f64 {
+    let coeff = 15. / (PI * smoothing_radius.powi(6));
+    coeff * (smoothing_radius - dist).powi(4)
+}
+
+pub fn near_grad(dist: f64, smoothing_radius: f64) -> f64 {
+    let coeff = -60. / (PI * smoothing_radius.powi(6));
+    coeff * (smoothing_radius - dist).powi(3)
+}
@@ -0,0 +1,214 @@
+use crate::sim::shape::{Shape, polygon_distance, segment_distance};
+
+// size of one cell of an ASCII-art mask, matching a character on screen
+const MASK_CELL_WIDTH: f64 = 1.;
+const MASK_CELL_HEIGHT: f64 = 2.;
+
+/// A static piece of the tank that particles bounce off.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Obstacle {
+    /// a wall from `a` to `b`, `thickness` wide
+    Segment {
+        a: (f64, f64),
+        b: (f64, f64),
+        thickness: f64,
+    },
+    Circle {
+        center: (f64, f64),
+        radius: f64,
+    },
+    /// a polygon with its vertices in tank coordinates
+    Polygon {
+        vertices: Vec<(f64, f64)>,
+    },
+    Mask(Mask),
+}
+
+/// A grid of solid cells rasterized from ASCII art, where every character other than a space
+/// is solid. Each character covers one cell on screen.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Mask {
+    origin: (f64, f64),
+    cells: Vec<Vec<bool>>,
+}
+
+impl Mask {
+    /// rasterizes `art` with its top-left corner at `origin`
+    pub fn new(origin: (f64, f64), art: &str) -> Self {
+        let cells = art
+            .lines()
+            .map(|line| line.chars().map(|c| c != ' ').collect())
+            .collect();
+        Self { origin, cells }
+    }
+
+    pub fn from_cells(origin: (f64, f64), cells: Vec<Vec<bool>>) -> Self {
+        Self { origin, cells }
+    }
+
+    /// the number of columns and rows of cells it takes to cover a `width` by `height` area
+    pub fn grid_size(width: f64, height: f64) -> (usize, usize) {
+        (
+            (width / MASK_CELL_WIDTH).ceil() as usize,
+            (height / MASK_CELL_HEIGHT).ceil() as usize,
+        )
+    }
+
+    /// rows of cells, which may be ragged
+    pub fn cells(&self) -> &[Vec<bool>] {
+        &self.cells
+    }
+
+    /// makes a cell solid or empty, growing the grid as needed
+    pub fn set(&mut self, col: usize, row: usize, solid: bool) {
+        if row >= self.cells.len() {
+            if !solid {
+                return;
+            }
+            self.cells.resize(row + 1, Vec::new());
+        }
+
+        let cells = &mut self.cells[row];
+        if col >= cells.len() {
+            if !solid {
+                return;
+            }
+            cells.resize(col + 1, false);
+        }
+        cells[col] = solid;
+    }
+
+    pub fn solid(&self, col: i64, row: i64) -> bool {
+        if col < 0 || row < 0 {
+            return false;
+        }
+        self.cells
+            .get(row as usize)
+            .and_then(|row| row.get(col as usize))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// signed distance to the nearest solid cell and the outward normal there, only looking as
+    /// far as the neighboring cells
+    pub fn distance(&self, p: (f64, f64)) -> (f64, (f64, f64)) {
+        let u = (p.0 - self.origin.0) / MASK_CELL_WIDTH;
+        let v = (p.1 - self.origin.1) / MASK_CELL_HEIGHT;
+        let (col, row) = (u.floor() as i64, v.floor() as i64);
+
+        // nothing to find that far from the grid, or for a point that has blown up
+        let cols = self.cells.iter().map(Vec::len).max().unwrap_or(0) as i64;
+        let rows = self.cells.len() as i64;
+        let near = u.is_finite()
+            && v.is_finite()
+            && (-1..=cols).contains(&col)
+            && (-1..=rows).contains(&row);
+        if !near {
+            return (f64::INFINITY, (0., -1.));
+        }
+
+        // cell bounds in tank coordinates
+        let left = self.origin.0 + col as f64 * MASK_CELL_WIDTH;
+        let top = self.origin.1 + row as f64 * MASK_CELL_HEIGHT;
+        let right = left + MASK_CELL_WIDTH;
+        let bottom = top + MASK_CELL_HEIGHT;
+
+        if self.solid(col, row) {
+            // out through the nearest face that isn't against another solid cell
+            let faces = [
+                (p.0 - left, (-1., 0.), (col - 1, row)),
+                (right - p.0, (1., 0.), (col + 1, row)),
+                (p.1 - top, (0., -1.), (col, row - 1)),
+                (bottom - p.1, (0., 1.), (col, row + 1)),
+            ];
+            let open = faces
+                .iter()
+                .filter(|(_, _, (c, r))| !self.solid(*c, *r))
+                .min_by(|a, b| a.0.total_cmp(&b.0));
+            let (dist, normal, _) = open
+                .or_else(|| faces.iter().min_by(|a, b| a.0.total_cmp(&b.0)))
+                .unwrap();
+            return (-dist, *normal);
+        }
+
+        let mut best = (f64::INFINITY, (0., -1.));
+        for c in col - 1..=col + 1 {
+            for r in row - 1..=row + 1 {
+                if !self.solid(c, r) {
+                    continue;
+                }
+                let min = (
+                    self.origin.0 + c as f64 * MASK_CELL_WIDTH,
+                    self.origin.1 + r as f64 * MASK_CELL_HEIGHT,
+                );
+                let closest = (
+                    p.0.clamp(min.0, min.0 + MASK_CELL_WIDTH),
+                    p.1.clamp(min.1, min.1 + MASK_CELL_HEIGHT),
+                );
+                let disp = (p.0 - closest.0, p.1 - closest.1);
+                let dist = disp.0.hypot(disp.1);
+                if dist < best.0 && dist > 0. {
+                    best = (dist, (disp.0 / dist, disp.1 / dist));
+                }
+            }
+        }
+        best
+    }
+}
+
+impl Obstacle {
+    /// Signed distance from `p` to the surface of the obstacle (negative inside), along with
+    /// the outward normal of the surface nearest to `p`.
+    pub fn distance(&self, p: (f64, f64)) -> (f64, (f64, f64)) {
+        match self {
+            Obstacle::Segment { a, b, thickness } => {
+                let (dist, normal) = segment_distance(p, *a, *b);
+                (dist - thickness / 2., normal)
+            }
+            Obstacle::Circle { center, radius } => {
+                Shape::Circle { radius: *radius }.distance((p.0 - center.0, p.1 - center.1))
+            }
+            Obstacle::Polygon { vertices } => polygon_distance(p, vertices),
+            Obstacle::Mask(mask) => mask.distance(p),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segment_distance() {
+        let segment = Obstacle::Segment {
+            a: (0., 0.),
+            b: (10., 0.),
+            thickness: 1.,
+        };
+        assert_eq!(segment.distance((5., 2.)), (1.5, (0., 1.)));
+        assert_eq!(segment.distance((5., -0.25)), (-0.25, (0., -1.)));
+    }
+
+    #[test]
+    fn mask_pushes_out_of_open_faces() {
+        // the top-right cell is closed off below and to the left
+        let mask = Obstacle::Mask(Mask::new((10., 10.), "##\n #"));
+        assert_eq!(mask.distance((11.5, 11.75)), (-0.5, (1., 0.)));
+        assert_eq!(mask.distance((11.25, 12.5)), (-0.25, (-1., 0.)));
+        assert_eq!(mask.distance((9.5, 11.)).0, 0.5);
+        assert!(mask.distance((20., 20.)).0.is_infinite());
+    }
+
+    #[test]
+    fn mask_ignores_blown_up_points() {
+        let mask = Obstacle::Mask(Mask::new((0., 0.), "#"));
+        for p in [
+            (f64::NAN, 0.5),
+            (0.5, f64::INFINITY),
+            (f64::NEG_INFINITY, 0.5),
+            (1e300, -1e300),
+        ] {
+            assert!(mask.distance(p).0.is_infinite());
+        }
+    }
+}
@@ -41,6 +41,14 @@ where
     pub fn reset(&mut self) {
         self.value = self.base;
     }
+
+    pub fn set(&mut self, value: T) {
+        self.value = value;
+    }
+
+    pub fn bounds(&self) -> (T, T) {
+        (self.min, self.max)
+    }
 }
 
 impl<T> Param<T>
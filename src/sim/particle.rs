@@ -1,6 +1,13 @@
+use crate::sim::integrator::Integrator;
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Particle {
     pos: (f64, f64),
     pub vel: (f64, f64),
+    // acceleration and length of the last step, which the leapfrog and Verlet integrators carry
+    // over into the next one (zero for a new particle, which starts them off cleanly)
+    accel: (f64, f64),
+    last_dt: f64,
 }
 
 impl Particle {
@@ -8,19 +15,79 @@ impl Particle {
         Self {
             pos: (x, y),
             vel: (vel_x, vel_y),
+            accel: (0., 0.),
+            last_dt: 0.,
+        }
+    }
+
+    /// carries over the acceleration and length of an earlier step, e.g. when restoring a
+    /// snapshot
+    pub fn with_last_step(mut self, accel: (f64, f64), last_dt: f64) -> Self {
+        self.accel = accel;
+        self.last_dt = last_dt;
+        self
+    }
+
+    /// Moves the particle forward by `dt_secs` under `accel`, which like the velocity points
+    /// against the motion.
+    pub fn integrate(&mut self, integrator: Integrator, accel: (f64, f64), dt_secs: f64) {
+        match integrator {
+            Integrator::Euler => {
+                self.update_vel(accel, dt_secs);
+                self.update_pos(dt_secs);
+            }
+            Integrator::Leapfrog => {
+                // kick from the middle of the last step to the middle of this one, then drift
+                self.update_vel(accel, 0.5 * (self.last_dt + dt_secs));
+                self.update_pos(dt_secs);
+            }
+            Integrator::VelocityVerlet => {
+                // the velocity was predicted with the last acceleration, so finish it with the
+                // average of that and this one
+                let correction = (accel.0 - self.accel.0, accel.1 - self.accel.1);
+                self.update_vel(correction, 0.5 * self.last_dt);
+
+                self.pos.0 -= (self.vel.0 + 0.5 * accel.0 * dt_secs) * dt_secs;
+                self.pos.1 -= (self.vel.1 + 0.5 * accel.1 * dt_secs) * dt_secs;
+                self.update_vel(accel, dt_secs);
+            }
         }
+        self.accel = accel;
+        self.last_dt = dt_secs;
     }
 
-    pub fn update_vel(&mut self, force: (f64, f64), dt_secs: f64) {
+    fn update_vel(&mut self, force: (f64, f64), dt_secs: f64) {
         self.vel.0 += force.0 * dt_secs;
         self.vel.1 += force.1 * dt_secs;
     }
 
-    pub fn update_pos(&mut self, dt_secs: f64) {
+    fn update_pos(&mut self, dt_secs: f64) {
         self.pos.1 -= self.vel.1 * dt_secs;
         self.pos.0 -= self.vel.0 * dt_secs;
     }
 
+    /// Moves the particle out of a surface it's `dist` inside of, along the surface's outward
+    /// `normal`, and bounces it off if it's moving into the surface faster than the surface
+    /// itself, keeping `dampening` of that speed.
+    pub fn collide(
+        &mut self,
+        dist: f64,
+        normal: (f64, f64),
+        surface_vel: (f64, f64),
+        dampening: f64,
+    ) {
+        self.pos.0 -= dist * normal.0;
+        self.pos.1 -= dist * normal.1;
+
+        // particle velocities point against their motion
+        let rel_vel = (-self.vel.0 - surface_vel.0, -self.vel.1 - surface_vel.1);
+        let normal_vel = rel_vel.0 * normal.0 + rel_vel.1 * normal.1;
+        if normal_vel < 0. {
+            self.vel.0 += (1. + dampening) * normal_vel * normal.0;
+            self.vel.1 += (1. + dampening) * normal_vel * normal.1;
+        }
+    }
+
     #[inline]
     pub fn x(&self) -> f64 {
         self.pos.0
@@ -41,6 +108,16 @@ impl Particle {
         self.vel.1
     }
 
+    /// acceleration of the last step
+    pub fn accel(&self) -> (f64, f64) {
+        self.accel
+    }
+
+    /// length of the last step
+    pub fn last_dt(&self) -> f64 {
+        self.last_dt
+    }
+
     #[inline]
     pub fn set_x(&mut self, x: f64) {
         self.pos.0 = x;
@@ -0,0 +1,108 @@
+use crate::sim::settings::Settings;
+
+/// A named set of parameter values. Parameters a preset doesn't mention take their defaults.
+pub struct Preset {
+    pub name: String,
+    pub values: Vec<(String, f64)>,
+}
+
+impl Preset {
+    pub fn new(name: impl Into<String>, values: Vec<(String, f64)>) -> Self {
+        Self {
+            name: name.into(),
+            values,
+        }
+    }
+
+    fn builtin(name: &str, values: &[(&str, f64)]) -> Self {
+        let values = values
+            .iter()
+            .map(|(key, value)| (key.to_string(), *value))
+            .collect();
+        Self::new(name, values)
+    }
+
+    pub fn apply(&self, settings: &mut Settings) -> anyhow::Result<()> {
+        let mut updated = Settings::default();
+        for (key, value) in &self.values {
+            updated.set_by_key(key, *value)?;
+        }
+
+        settings.load_values(&updated, &self.name);
+        Ok(())
+    }
+}
+
+pub fn builtin_presets() -> Vec<Preset> {
+    vec![
+        Preset::builtin(
+            "water",
+            &[
+                ("viscosity", 0.8),
+                ("stiffness", 3500.),
+                ("dampening", 0.05),
+            ],
+        ),
+        Preset::builtin(
+            "oil",
+            &[
+                ("density", 0.9),
+                ("viscosity", 6.),
+                ("stiffness", 2500.),
+                ("dampening", 0.1),
+            ],
+        ),
+        Preset::builtin(
+            "honey",
+            &[
+                ("gravity", 12.),
+                ("density", 1.4),
+                ("viscosity", 18.),
+                ("stiffness", 2000.),
+                ("dampening", 0.3),
+            ],
+        ),
+        Preset::builtin(
+            "zero-g",
+            &[("gravity", 0.), ("viscosity", 1.), ("dampening", 0.5)],
+        ),
+        Preset::builtin(
+            "gas-like",
+            &[
+                ("particles", 3000.),
+                ("gravity", 2.),
+                ("density", 0.2),
+                ("viscosity", 0.2),
+                ("stiffness", 6000.),
+                ("dampening", 1.),
+            ],
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_presets_are_valid() {
+        for preset in builtin_presets() {
+            let mut settings = Settings::default();
+            preset.apply(&mut settings).unwrap();
+            assert_eq!(settings.preset(), Some(preset.name.as_str()));
+        }
+    }
+
+    #[test]
+    fn apply_resets_unlisted_params() {
+        let mut settings = Settings::default();
+        settings.set_by_key("stiffness", 100.).unwrap();
+
+        Preset::builtin("floaty", &[("gravity", 1.)])
+            .apply(&mut settings)
+            .unwrap();
+
+        assert_eq!(settings.gravity(), 1.);
+        assert_eq!(settings.stiffness(), Settings::default().stiffness());
+    }
+}
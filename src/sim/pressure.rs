@@ -0,0 +1,63 @@
+use clap::ValueEnum;
+
+use crate::sim::constants::NEAR_STIFFNESS;
+
+/// How the pressure on each particle is worked out from the density around it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum PressureModel {
+    /// pushes apart where the fluid is denser than the target, but never pulls together unless
+    /// negative pressure is allowed
+    #[default]
+    Clamped,
+    /// also pulls together where the fluid is too thin, with a near-pressure between close
+    /// neighbors that stops them clumping (after Clavet et al. 2005)
+    DoubleDensity,
+}
+
+impl PressureModel {
+    /// name shown in the info panel
+    pub fn label(self) -> &'static str {
+        match self {
+            PressureModel::Clamped => "Clamped",
+            PressureModel::DoubleDensity => "Double density",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            PressureModel::Clamped => PressureModel::DoubleDensity,
+            PressureModel::DoubleDensity => PressureModel::Clamped,
+        }
+    }
+
+    /// whether the model needs the near-density of each particle
+    pub fn uses_near_density(self) -> bool {
+        self == PressureModel::DoubleDensity
+    }
+
+    /// pressure from the equation of state, kept from pulling where the model doesn't allow it
+    pub fn pressure(self, pressure: f64, negative_pressure: bool) -> f64 {
+        match self {
+            PressureModel::Clamped if !negative_pressure => pressure.max(0.),
+            _ => pressure,
+        }
+    }
+
+    /// near-pressure, which only ever pushes apart
+    pub fn near_pressure(self, near_density: f64, stiffness: f64) -> f64 {
+        NEAR_STIFFNESS * stiffness * near_density
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_double_density_pulls() {
+        assert_eq!(PressureModel::Clamped.pressure(-5., false), 0.);
+        assert_eq!(PressureModel::Clamped.pressure(-5., true), -5.);
+        assert_eq!(PressureModel::DoubleDensity.pressure(-5., false), -5.);
+        assert_eq!(PressureModel::DoubleDensity.pressure(5., false), 5.);
+    }
+}
@@ -1,21 +1,273 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
-use crate::sim::{Simulation, constants::TIMESTEP_MS, settings::Settings};
+use crate::{
+    recording::InputSource,
+    sim::{
+        Simulation,
+        constants::{HISTORY_INTERVAL_MS, HISTORY_SECS, MAX_CATCH_UP_STEPS, SUBSTEPS, TIMESTEP_MS},
+        history::History,
+        settings::Settings,
+    },
+};
 
-pub fn run_sim_loop(sim: Arc<Mutex<Simulation>>, settings: Arc<Mutex<Settings>>) {
-    let mut time = std::time::Instant::now();
+/// How wall-clock time is turned into simulation steps.
+#[derive(Clone, Copy, Debug)]
+pub struct Timestep {
+    /// length of one fixed step
+    pub step: Duration,
+    /// number of `Simulation::update` calls each fixed step is split into
+    pub substeps: usize,
+    /// maximum number of fixed steps run at once when catching up after a stall
+    pub max_catch_up: usize,
+}
+
+impl Default for Timestep {
+    fn default() -> Self {
+        Self {
+            step: Duration::from_millis(TIMESTEP_MS),
+            substeps: SUBSTEPS,
+            max_catch_up: MAX_CATCH_UP_STEPS,
+        }
+    }
+}
+
+impl Timestep {
+    pub fn substep_secs(&self) -> f64 {
+        self.step.as_secs_f64() / self.substeps as f64
+    }
+}
+
+/// Pause state of the sim loop, shared with the event loop.
+#[derive(Default)]
+pub struct Playback {
+    paused: bool,
+    pending_steps: usize,
+    pending_scrub: isize,
+    rewound_secs: f64,
+    replaying: bool,
+}
+
+impl Playback {
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.pending_steps = 0;
+    }
+
+    /// asks for exactly one more `Simulation::update` while paused
+    pub fn request_step(&mut self) {
+        if self.paused {
+            self.pending_steps += 1;
+        }
+    }
+
+    /// asks to move `frames` through the rewind history (negative is backward) while paused
+    pub fn scrub(&mut self, frames: isize) {
+        if self.paused {
+            self.pending_scrub += frames;
+        }
+    }
+
+    /// whether a replayed log is still driving the sim
+    pub fn replaying(&self) -> bool {
+        self.replaying
+    }
+
+    /// how far back in the history the sim has been rewound
+    pub fn rewound_secs(&self) -> f64 {
+        self.rewound_secs
+    }
+
+    fn take_pending_steps(&mut self) -> usize {
+        std::mem::take(&mut self.pending_steps)
+    }
+
+    fn take_pending_scrub(&mut self) -> isize {
+        std::mem::take(&mut self.pending_scrub)
+    }
+}
+
+/// Collects elapsed wall-clock time and hands it back out in whole fixed steps.
+pub struct Accumulator {
+    timestep: Timestep,
+    accumulated: Duration,
+}
+
+impl Accumulator {
+    pub fn new(timestep: Timestep) -> Self {
+        Self {
+            timestep,
+            accumulated: Duration::ZERO,
+        }
+    }
+
+    /// adds `elapsed` to the accumulator and returns how many fixed steps are due
+    pub fn advance(&mut self, elapsed: Duration) -> usize {
+        self.accumulated += elapsed;
+
+        // drop any time we can't catch up on, rather than spiralling after a stall
+        let max_backlog = u32::try_from(self.timestep.max_catch_up)
+            .ok()
+            .and_then(|steps| self.timestep.step.checked_mul(steps))
+            .unwrap_or(Duration::MAX);
+        if self.accumulated > max_backlog {
+            self.accumulated = max_backlog;
+        }
+
+        let steps = (self.accumulated.as_nanos() / self.timestep.step.as_nanos()) as usize;
+        self.accumulated -= self.timestep.step * steps as u32;
+        steps
+    }
+
+    /// throws away any accumulated time, e.g. while paused
+    pub fn reset(&mut self) {
+        self.accumulated = Duration::ZERO;
+    }
+
+    /// time left until the next fixed step is due
+    pub fn until_next_step(&self) -> Duration {
+        self.timestep.step.saturating_sub(self.accumulated)
+    }
+}
+
+pub fn run_sim_loop(
+    sim: Arc<Mutex<Simulation>>,
+    settings: Arc<Mutex<Settings>>,
+    playback: Arc<Mutex<Playback>>,
+    timestep: Timestep,
+    mut inputs: InputSource,
+) {
+    let mut accumulator = Accumulator::new(timestep);
+    let dt_secs = timestep.substep_secs();
+    let mut time = Instant::now();
+
+    // keep a frame of history every `history_interval` updates
+    let history_interval = ((HISTORY_INTERVAL_MS as f64 / 1000. / dt_secs).round() as u64).max(1);
+    let mut history = History::new((HISTORY_SECS * 1000 / HISTORY_INTERVAL_MS) as usize);
 
     loop {
-        let dt = time.elapsed();
-        time = std::time::Instant::now();
+        let now = Instant::now();
+        let elapsed = now - time;
+        time = now;
+
+        let time_scale = settings.lock().unwrap().time_scale();
+
+        // while paused, only run the single steps that were asked for
+        let mut playback_lock = playback.lock().unwrap();
+        playback_lock.replaying = inputs.replaying();
+        let (updates, scrub) = if playback_lock.paused() {
+            accumulator.reset();
+            (
+                playback_lock.take_pending_steps(),
+                playback_lock.take_pending_scrub(),
+            )
+        } else {
+            // scaling the elapsed time speeds up or slows down the sim without changing `dt`
+            let steps = accumulator.advance(elapsed.mul_f64(time_scale));
+            (steps * timestep.substeps, 0)
+        };
+        drop(playback_lock);
 
-        {
+        // a restore isn't an input, so a recorded run can't be rewound without the log going
+        // out of step with the sim
+        let scrub = if inputs.recording() { 0 } else { scrub };
+
+        if updates > 0 || scrub != 0 {
             let mut sim = sim.lock().unwrap();
-            let settings = settings.lock().unwrap();
-            sim.update(dt.as_secs_f64(), &settings);
+            let mut settings = settings.lock().unwrap();
+
+            if let Some(frame) = history.scrub(scrub, || sim.snapshot()) {
+                sim.restore(frame);
+                settings.set_particle_count(sim.particles().len());
+                settings.set_fish_count(sim.fish().len());
+            }
+
+            // carry on from wherever the history was scrubbed to
+            if updates > 0 {
+                history.resume();
+            }
+            for _ in 0..updates {
+                inputs.apply(&mut sim, &mut settings);
+                sim.update(dt_secs, &settings);
+                // the faucet and sponge change the count, which the Particles param should show
+                settings.set_particle_count(sim.particles().len());
+                if sim.step().is_multiple_of(history_interval) {
+                    history.push(sim.snapshot());
+                }
+            }
+
+            let rewound_secs = history.frames_back() as f64 * HISTORY_INTERVAL_MS as f64 / 1000.;
+            playback.lock().unwrap().rewound_secs = rewound_secs;
+        }
+
+        let until_next_step = accumulator.until_next_step().div_f64(time_scale);
+        std::thread::sleep(until_next_step.saturating_sub(time.elapsed()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timestep() -> Timestep {
+        Timestep {
+            step: Duration::from_millis(10),
+            substeps: 2,
+            max_catch_up: 3,
         }
+    }
+
+    #[test]
+    fn accumulate_partial_steps() {
+        let mut accumulator = Accumulator::new(timestep());
+
+        assert_eq!(accumulator.advance(Duration::from_millis(6)), 0);
+        assert_eq!(accumulator.until_next_step(), Duration::from_millis(4));
+        assert_eq!(accumulator.advance(Duration::from_millis(6)), 1);
+        assert_eq!(accumulator.until_next_step(), Duration::from_millis(8));
+    }
+
+    #[test]
+    fn cap_catch_up_after_stall() {
+        let mut accumulator = Accumulator::new(timestep());
+
+        assert_eq!(accumulator.advance(Duration::from_secs(2)), 3);
+        assert_eq!(accumulator.advance(Duration::from_millis(5)), 0);
+    }
+
+    #[test]
+    fn huge_catch_up_cap_does_not_overflow() {
+        let mut accumulator = Accumulator::new(Timestep {
+            step: Duration::from_secs(5),
+            substeps: 1,
+            max_catch_up: usize::MAX,
+        });
+
+        assert_eq!(accumulator.advance(Duration::from_secs(12)), 2);
+    }
+
+    #[test]
+    fn single_step_only_while_paused() {
+        let mut playback = Playback::default();
+
+        playback.request_step();
+        assert_eq!(playback.take_pending_steps(), 0);
+
+        playback.toggle_pause();
+        playback.request_step();
+        playback.request_step();
+        assert_eq!(playback.take_pending_steps(), 2);
+        assert_eq!(playback.take_pending_steps(), 0);
+    }
 
-        // FIXME: need to take processing time into account here
-        std::thread::sleep(std::time::Duration::from_millis(TIMESTEP_MS));
+    #[test]
+    fn split_step_into_substeps() {
+        assert_eq!(timestep().substep_secs(), 0.005);
     }
 }
@@ -1,22 +1,139 @@
+use clap::ValueEnum;
 use rand::Rng;
 
 use crate::sim::Simulation;
 
-pub fn add_dense_square(sim: &mut Simulation, center: (f64, f64), radius: i32) {
-    for i in -radius..=radius {
-        for j in -radius..=radius {
-            sim.add_particle(center.0 + (i as f64) / 2., center.1 + (j as f64) / 2.);
-        }
+// spacing between particles in the packed seed patterns
+const PACKED_SPACING: f64 = 0.5;
+
+/// Initial arrangements of particles in the tank.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum SeedPattern {
+    /// scattered randomly over the whole tank
+    #[default]
+    Uniform,
+    /// a densely packed square in the middle of the tank
+    Square,
+    /// a column of fluid against the left wall, ready to collapse
+    Dam,
+}
+
+pub fn seed(sim: &mut Simulation, pattern: SeedPattern, count: usize) {
+    let (width, height) = (sim.width, sim.height);
+
+    match pattern {
+        SeedPattern::Uniform => add_uniform_points(sim, count, width, height),
+        SeedPattern::Square => add_dense_square(sim, (width / 2., height / 2.), count),
+        SeedPattern::Dam => add_dam(sim, count, width, height),
+    }
+}
+
+/// packs `count` particles into a square around `center`, filling it row by row. The square is
+/// packed closer together if it wouldn't fit in the tank, and moved in from the edges if it
+/// would stick out.
+pub fn add_dense_square(sim: &mut Simulation, center: (f64, f64), count: usize) {
+    let side = (count as f64).sqrt().ceil() as usize;
+    let spacing = PACKED_SPACING.min(sim.width.min(sim.height) / side.max(1) as f64);
+    // capped, as rounding can leave the square a hair wider than the tank
+    let half = side as f64 * spacing / 2.;
+    let (half_x, half_y) = (half.min(sim.width / 2.), half.min(sim.height / 2.));
+    let center = (
+        center.0.clamp(half_x, sim.width - half_x),
+        center.1.clamp(half_y, sim.height - half_y),
+    );
+    let offset = (side as f64 - 1.) / 2.;
+
+    for idx in 0..count {
+        sim.add_particle(
+            center.0 + ((idx % side) as f64 - offset) * spacing,
+            center.1 + ((idx / side) as f64 - offset) * spacing,
+        );
     }
 }
 
 // FIXME: no need to pass width/height here, `sim` has that info
 pub fn add_uniform_points(sim: &mut Simulation, count: usize, width: f64, height: f64) {
-    let mut rng = rand::rng();
-
     for _ in 0..count {
-        let x = rng.random_range(0.0..width);
-        let y = rng.random_range(0.0..height);
+        let x = sim.rng.random_range(0.0..width);
+        let y = sim.rng.random_range(0.0..height);
+        sim.add_particle(x, y);
+    }
+}
+
+// packs particles against the left wall, stacking upwards from the bottom over a third of the
+// tank's width. Once they reach the top the dam widens, and if even the whole tank isn't enough
+// they're packed closer together, so that no two particles start on the same spot.
+fn add_dam(sim: &mut Simulation, count: usize, width: f64, height: f64) {
+    let fits = |spacing: f64| ((width / spacing) as usize) * ((height / spacing) as usize) >= count;
+    let mut spacing = PACKED_SPACING;
+    while !fits(spacing) {
+        spacing *= 0.95;
+    }
+    let rows = ((height / spacing) as usize).max(1);
+    let cols = ((width / 3. / spacing) as usize)
+        .max(count.div_ceil(rows))
+        .max(1);
+
+    for idx in 0..count {
+        let x = (idx % cols) as f64 * spacing + spacing / 2.;
+        let y = height - (idx / cols) as f64 * spacing - spacing / 2.;
         sim.add_particle(x, y);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seeded(pattern: SeedPattern, count: usize) -> Simulation {
+        let mut sim = Simulation::with_seed(80., 48., 0);
+        seed(&mut sim, pattern, count);
+        sim
+    }
+
+    #[test]
+    fn seed_exact_count() {
+        for pattern in [SeedPattern::Uniform, SeedPattern::Square, SeedPattern::Dam] {
+            assert_eq!(seeded(pattern, 10000).particles().len(), 10000);
+        }
+    }
+
+    fn in_tank(sim: &Simulation) -> bool {
+        sim.particles().iter().all(|pt| {
+            (0.0..=sim.width()).contains(&pt.x()) && (0.0..=sim.height()).contains(&pt.y())
+        })
+    }
+
+    #[test]
+    fn square_fits_in_the_tank() {
+        for count in [100, 10000, 20000] {
+            assert!(in_tank(&seeded(SeedPattern::Square, count)));
+        }
+
+        // squeezed into a corner
+        let mut sim = Simulation::with_seed(80., 48., 0);
+        add_dense_square(&mut sim, (0., 48.), 400);
+        assert!(in_tank(&sim));
+
+        // filling a narrow tank edge to edge
+        let mut sim = Simulation::with_seed(7., 48., 0);
+        seed(&mut sim, SeedPattern::Square, 10000);
+        assert!(in_tank(&sim));
+    }
+
+    #[test]
+    fn dam_particles_stay_apart_in_the_tank() {
+        for count in [100, 10000, 20000] {
+            let sim = seeded(SeedPattern::Dam, count);
+            let mut points = sim
+                .particles()
+                .iter()
+                .map(|pt| (pt.x().to_bits(), pt.y().to_bits()))
+                .collect::<Vec<_>>();
+            points.sort();
+            points.dedup();
+            assert_eq!(points.len(), count);
+            assert!(in_tank(&sim));
+        }
+    }
+}
@@ -1,6 +1,13 @@
-use crate::sim::param::Param;
+use anyhow::{anyhow, ensure};
+use clap::ValueEnum;
+
+use crate::sim::{
+    boundary::Boundaries, eos::Eos, integrator::Integrator, param::Param,
+    pressure::PressureModel, solver::Solver,
+};
 
 const SETTINGS_WIDTH: usize = 26;
+const NUM_SETTINGS: usize = 16;
 
 pub struct Settings {
     particle_count: Param<f64>,
@@ -12,7 +19,21 @@ pub struct Settings {
     viscosity: Param<f64>,
     mouse_force_strength: Param<f64>,
     mouse_force_radius: Param<f64>,
-
+    time_scale: Param<f64>,
+    fish_count: Param<f64>,
+    gravity_angle: Param<f64>,
+    cohesion: Param<f64>,
+    tait_gamma: Param<f64>,
+    xsph: Param<f64>,
+    artificial_viscosity: Param<f64>,
+    boundaries: Boundaries,
+    pressure_model: PressureModel,
+    eos: Eos,
+    negative_pressure: bool,
+    solver: Solver,
+    integrator: Integrator,
+
+    preset: Option<String>,
     selected_idx: usize,
     visible: bool,
 }
@@ -21,7 +42,7 @@ impl Default for Settings {
     fn default() -> Self {
         Self {
             particle_count: Param::default()
-                .min(500.)
+                .min(0.)
                 .max(20000.)
                 .step(500.)
                 .base(10000.),
@@ -33,7 +54,21 @@ impl Default for Settings {
             viscosity: Param::default().min(0.).max(20.0).step(0.1).base(2.0),
             mouse_force_strength: Param::default().min(0.).max(20.0).step(0.5).base(3.0),
             mouse_force_radius: Param::default().min(5.0).max(50.0).step(1.0).base(15.0),
-
+            time_scale: Param::default().min(0.1).max(4.0).step(0.1).base(1.0),
+            fish_count: Param::default().min(0.).max(30.).step(1.).base(5.),
+            gravity_angle: Param::default().min(-90.).max(90.).step(5.).base(0.),
+            cohesion: Param::default().min(0.).max(500.).step(10.).base(0.),
+            tait_gamma: Param::default().min(1.).max(7.).step(1.).base(7.),
+            xsph: Param::default().min(0.).max(0.5).step(0.05).base(0.),
+            artificial_viscosity: Param::default().min(0.).max(0.5).step(0.01).base(0.),
+            boundaries: Boundaries::default(),
+            pressure_model: PressureModel::default(),
+            eos: Eos::default(),
+            negative_pressure: false,
+            solver: Solver::default(),
+            integrator: Integrator::default(),
+
+            preset: None,
             selected_idx: 0,
             visible: false,
         }
@@ -42,7 +77,7 @@ impl Default for Settings {
 
 impl Settings {
     // metadata for rendering
-    pub const NAMES: [&'static str; 9] = [
+    pub const NAMES: [&'static str; NUM_SETTINGS] = [
         "Particles",
         "Gravity",
         "Density",
@@ -52,13 +87,48 @@ impl Settings {
         "Dampening",
         "Mouse Force",
         "Mouse Radius",
+        "Time Scale",
+        "Fish",
+        "Gravity Angle",
+        "Cohesion",
+        "Tait Gamma",
+        "XSPH",
+        "Art Viscosity",
+    ];
+    pub const PRECISIONS: [usize; NUM_SETTINGS] = [0, 1, 1, 1, 0, 1, 2, 1, 0, 1, 0, 0, 0, 0, 2, 2];
+    pub const PARTICLE_COUNT_IDX: usize = 0;
+    pub const TIME_SCALE_IDX: usize = 9;
+    pub const FISH_COUNT_IDX: usize = 10;
+    pub const GRAVITY_ANGLE_IDX: usize = 11;
+    // names used on the command line
+    pub const KEYS: [&'static str; NUM_SETTINGS] = [
+        "particles",
+        "gravity",
+        "density",
+        "viscosity",
+        "stiffness",
+        "smoothing-radius",
+        "dampening",
+        "mouse-force",
+        "mouse-radius",
+        "time-scale",
+        "fish",
+        "gravity-angle",
+        "cohesion",
+        "tait-gamma",
+        "xsph",
+        "artificial-viscosity",
     ];
-    pub const PRECISIONS: [usize; 9] = [0, 1, 1, 1, 0, 1, 2, 1, 0];
 
     pub fn particle_count(&self) -> usize {
         (*self.particle_count.value()) as usize
     }
 
+    /// most particles the tank can hold
+    pub fn max_particle_count(&self) -> usize {
+        self.particle_count.bounds().1 as usize
+    }
+
     pub fn gravity(&self) -> f64 {
         *self.gravity.value()
     }
@@ -91,7 +161,94 @@ impl Settings {
         *self.mouse_force_radius.value()
     }
 
+    pub fn time_scale(&self) -> f64 {
+        *self.time_scale.value()
+    }
+
+    pub fn fish_count(&self) -> usize {
+        (*self.fish_count.value()) as usize
+    }
+
+    /// tilt of the tank in degrees, where positive angles pull the water right
+    pub fn gravity_angle(&self) -> f64 {
+        *self.gravity_angle.value()
+    }
+
+    pub fn cohesion(&self) -> f64 {
+        *self.cohesion.value()
+    }
+
+    pub fn tait_gamma(&self) -> f64 {
+        *self.tait_gamma.value()
+    }
+
+    pub fn xsph(&self) -> f64 {
+        *self.xsph.value()
+    }
+
+    pub fn artificial_viscosity(&self) -> f64 {
+        *self.artificial_viscosity.value()
+    }
+
+    pub fn boundaries(&self) -> Boundaries {
+        self.boundaries
+    }
+
+    pub fn set_boundaries(&mut self, boundaries: Boundaries) -> anyhow::Result<()> {
+        boundaries.check()?;
+        self.boundaries = boundaries;
+        Ok(())
+    }
+
+    pub fn pressure_model(&self) -> PressureModel {
+        self.pressure_model
+    }
+
+    pub fn set_pressure_model(&mut self, model: PressureModel) {
+        self.pressure_model = model;
+    }
+
+    pub fn eos(&self) -> Eos {
+        self.eos
+    }
+
+    pub fn set_eos(&mut self, eos: Eos) {
+        self.eos = eos;
+    }
+
+    /// whether pressure may pull the fluid together, with a correction that keeps particles
+    /// from clumping where it does
+    pub fn negative_pressure(&self) -> bool {
+        self.negative_pressure
+    }
+
+    pub fn set_negative_pressure(&mut self, negative_pressure: bool) {
+        self.negative_pressure = negative_pressure;
+    }
+
+    pub fn solver(&self) -> Solver {
+        self.solver
+    }
+
+    pub fn set_solver(&mut self, solver: Solver) {
+        self.solver = solver;
+    }
+
+    pub fn integrator(&self) -> Integrator {
+        self.integrator
+    }
+
+    pub fn set_integrator(&mut self, integrator: Integrator) {
+        self.integrator = integrator;
+    }
+
     // computed values
+    /// gravity pointing the way it pulls, straight down when the tank is level
+    pub fn gravity_vec(&self) -> (f64, f64) {
+        let (sin, cos) = self.gravity_angle().to_radians().sin_cos();
+        (self.gravity() * sin, self.gravity() * cos)
+    }
+
     pub fn smoothing_radius_sq(&self) -> f64 {
         let r = self.smoothing_radius();
         r * r
@@ -102,7 +259,12 @@ impl Settings {
     }
 
     pub const fn num_settings() -> usize {
-        9
+        NUM_SETTINGS
+    }
+
+    /// name of the last preset applied, cleared once a parameter is changed by hand
+    pub fn preset(&self) -> Option<&str> {
+        self.preset.as_deref()
     }
 
     pub fn selected_idx(&self) -> usize {
@@ -126,7 +288,7 @@ impl Settings {
     }
 
     // helper methods for iteration
-    pub fn params(&self) -> [&Param<f64>; 9] {
+    pub fn params(&self) -> [&Param<f64>; NUM_SETTINGS] {
         [
             &self.particle_count,
             &self.gravity,
@@ -137,10 +299,17 @@ impl Settings {
             &self.dampening,
             &self.mouse_force_strength,
             &self.mouse_force_radius,
+            &self.time_scale,
+            &self.fish_count,
+            &self.gravity_angle,
+            &self.cohesion,
+            &self.tait_gamma,
+            &self.xsph,
+            &self.artificial_viscosity,
         ]
     }
 
-    fn params_mut(&mut self) -> [&mut Param<f64>; 9] {
+    fn params_mut(&mut self) -> [&mut Param<f64>; NUM_SETTINGS] {
         [
             &mut self.particle_count,
             &mut self.gravity,
@@ -151,6 +320,13 @@ impl Settings {
             &mut self.dampening,
             &mut self.mouse_force_strength,
             &mut self.mouse_force_radius,
+            &mut self.time_scale,
+            &mut self.fish_count,
+            &mut self.gravity_angle,
+            &mut self.cohesion,
+            &mut self.tait_gamma,
+            &mut self.xsph,
+            &mut self.artificial_viscosity,
         ]
     }
 
@@ -172,16 +348,48 @@ impl Settings {
         if !self.visible {
             return;
         }
-        let idx = self.selected_idx;
-        self.params_mut()[idx].inc();
+        self.inc_param(self.selected_idx);
     }
 
     pub fn dec_selected(&mut self) {
         if !self.visible {
             return;
         }
-        let idx = self.selected_idx;
+        self.dec_param(self.selected_idx);
+    }
+
+    pub fn inc_param(&mut self, idx: usize) {
+        self.params_mut()[idx].inc();
+        self.preset = None;
+    }
+
+    pub fn dec_param(&mut self, idx: usize) {
         self.params_mut()[idx].dec();
+        self.preset = None;
+    }
+
+    /// sets the value of the parameter at `idx`, failing if it is outside the parameter's range
+    pub fn set_value(&mut self, idx: usize, value: f64) -> anyhow::Result<()> {
+        let param = &mut self.params_mut()[idx];
+        let (min, max) = param.bounds();
+        ensure!(
+            (min..=max).contains(&value),
+            "{} must be between {} and {}, got {}",
+            Self::KEYS[idx],
+            min,
+            max,
+            value
+        );
+        param.set(value);
+        Ok(())
+    }
+
+    pub fn set_by_key(&mut self, key: &str, value: f64) -> anyhow::Result<()> {
+        let idx = Self::KEYS
+            .iter()
+            .position(|k| *k == key)
+            .ok_or_else(|| anyhow!("unknown setting `{}`", key))?;
+        self.set_value(idx, value)
     }
 
     pub fn reset_selected(&mut self) {
@@ -190,6 +398,26 @@ impl Settings {
         }
         let idx = self.selected_idx;
         self.params_mut()[idx].reset();
+        self.preset = None;
+    }
+
+    /// sets the particle count to match the simulation, clamped to the parameter's range
+    pub fn set_particle_count(&mut self, count: usize) {
+        let (min, max) = self.particle_count.bounds();
+        self.particle_count.set((count as f64).clamp(min, max));
+    }
+
+    pub fn set_fish_count(&mut self, count: usize) {
+        let (min, max) = self.fish_count.bounds();
+        self.fish_count.set((count as f64).clamp(min, max));
+    }
+
+    /// copies every parameter value from `other`, remembering the name of the preset they came from
+    pub fn load_values(&mut self, other: &Settings, preset: &str) {
+        for (param, other) in self.params_mut().into_iter().zip(other.params()) {
+            param.set(*other.value());
+        }
+        self.preset = Some(preset.to_string());
     }
 
     pub fn render(&self) -> String {
@@ -203,9 +431,15 @@ impl Settings {
 
         const CONTENT_WIDTH: usize = SETTINGS_WIDTH - 2;
 
-        // top border
+        // top border, with the preset name if there is one
+        let title = match &self.preset {
+            Some(name) => format!("─ {} ", name),
+            None => String::new(),
+        };
+        let title = title.chars().take(CONTENT_WIDTH).collect::<String>();
         out.push('┌');
-        out.push_str(&"─".repeat(CONTENT_WIDTH));
+        out.push_str(&title);
+        out.push_str(&"─".repeat(CONTENT_WIDTH - title.chars().count()));
         out.push('┐');
 
         // settings rows
@@ -249,6 +483,19 @@ impl Settings {
     }
 }
 
+/// the name a choice like the solver goes by on the command line, and in recordings and profiles
+pub fn choice_name<T: ValueEnum>(choice: &T) -> String {
+    choice.to_possible_value().unwrap().get_name().to_string()
+}
+
+/// parses a choice from the name [`choice_name`] gives it
+pub fn parse_choice<T: ValueEnum>(name: &str) -> anyhow::Result<T> {
+    T::from_str(name, false).map_err(|_| {
+        let names = T::value_variants().iter().map(choice_name).collect::<Vec<_>>();
+        anyhow!("`{}` is not one of {}", name, names.join(", "))
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -276,4 +523,43 @@ mod tests {
         settings.select_next();
         assert_eq!(settings.selected_idx, 0);
     }
+
+    #[test]
+    fn render_preset_title() {
+        let mut settings = Settings::default();
+        settings.toggle_visibility();
+        settings.load_values(&Settings::default(), "a very long preset name indeed");
+
+        let out = settings.render();
+        assert!(out.starts_with("┌─ a very long"));
+        assert_eq!(
+            out.chars().count(),
+            Settings::render_width() * Settings::render_height()
+        );
+    }
+
+    #[test]
+    fn set_by_key() {
+        let mut settings = Settings::default();
+
+        settings.set_by_key("gravity", 20.).unwrap();
+        assert_eq!(settings.gravity(), 20.);
+
+        let err = settings.set_by_key("gravity", 51.).unwrap_err();
+        assert_eq!(err.to_string(), "gravity must be between 0 and 50, got 51");
+        assert_eq!(settings.gravity(), 20.);
+
+        assert!(settings.set_by_key("gravitas", 1.).is_err());
+    }
+
+    #[test]
+    fn tilt_gravity() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.gravity_vec(), (0., settings.gravity()));
+
+        settings.set_by_key("gravity-angle", -90.).unwrap();
+        let (x, y) = settings.gravity_vec();
+        assert_eq!(x, -settings.gravity());
+        assert!(y.abs() < 1e-9);
+    }
 }
@@ -0,0 +1,179 @@
+/// A solid shape in its own local coordinates, centered on its centroid.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    Circle {
+        radius: f64,
+    },
+    /// a convex or concave polygon, with vertices in order around it
+    Polygon {
+        vertices: Vec<(f64, f64)>,
+    },
+}
+
+impl Shape {
+    pub fn rect(width: f64, height: f64) -> Self {
+        let (w, h) = (width / 2., height / 2.);
+        Self::Polygon {
+            vertices: vec![(-w, -h), (w, -h), (w, h), (-w, h)],
+        }
+    }
+
+    /// a polygon from the given vertices, shifted so that its centroid is at the origin
+    pub fn polygon(vertices: Vec<(f64, f64)>) -> Self {
+        let area = polygon_area(&vertices);
+        let (mut cx, mut cy) = (0., 0.);
+        for (a, b) in edges(&vertices) {
+            let cross = a.0 * b.1 - b.0 * a.1;
+            cx += (a.0 + b.0) * cross;
+            cy += (a.1 + b.1) * cross;
+        }
+        let (cx, cy) = (cx / (6. * area), cy / (6. * area));
+
+        Self::Polygon {
+            vertices: vertices.iter().map(|v| (v.0 - cx, v.1 - cy)).collect(),
+        }
+    }
+
+    pub fn area(&self) -> f64 {
+        match self {
+            Shape::Circle { radius } => std::f64::consts::PI * radius * radius,
+            Shape::Polygon { vertices } => polygon_area(vertices).abs(),
+        }
+    }
+
+    /// moment of inertia about the centroid for a unit mass
+    pub fn unit_inertia(&self) -> f64 {
+        match self {
+            Shape::Circle { radius } => radius * radius / 2.,
+            Shape::Polygon { vertices } => {
+                let (mut num, mut den) = (0., 0.);
+                for (a, b) in edges(vertices) {
+                    let cross = (a.0 * b.1 - b.0 * a.1).abs();
+                    num += cross
+                        * (a.0 * a.0 + a.0 * b.0 + b.0 * b.0 + a.1 * a.1 + a.1 * b.1 + b.1 * b.1);
+                    den += cross;
+                }
+                num / (6. * den)
+            }
+        }
+    }
+
+    /// distance from the centroid to the furthest point of the shape
+    pub fn bounding_radius(&self) -> f64 {
+        match self {
+            Shape::Circle { radius } => *radius,
+            Shape::Polygon { vertices } => {
+                vertices.iter().map(|v| v.0.hypot(v.1)).fold(0., f64::max)
+            }
+        }
+    }
+
+    /// Signed distance from `p` to the surface of the shape (negative inside), along with the
+    /// outward normal of the surface nearest to `p`.
+    pub fn distance(&self, p: (f64, f64)) -> (f64, (f64, f64)) {
+        match self {
+            Shape::Circle { radius } => {
+                let dist = p.0.hypot(p.1);
+                let normal = if dist > 0. {
+                    (p.0 / dist, p.1 / dist)
+                } else {
+                    (0., -1.)
+                };
+                (dist - radius, normal)
+            }
+            Shape::Polygon { vertices } => polygon_distance(p, vertices),
+        }
+    }
+}
+
+/// signed distance from `p` to the edges of a polygon, and the outward normal there
+pub fn polygon_distance(p: (f64, f64), vertices: &[(f64, f64)]) -> (f64, (f64, f64)) {
+    let mut best = (f64::INFINITY, (0., -1.));
+    let mut inside = false;
+    for (a, b) in edges(vertices) {
+        let (dist, normal) = segment_distance(p, a, b);
+        if dist < best.0 {
+            best = (dist, normal);
+        }
+
+        // crossing test for whether the point is inside
+        if (a.1 > p.1) != (b.1 > p.1) && p.0 < a.0 + (p.1 - a.1) * (b.0 - a.0) / (b.1 - a.1) {
+            inside = !inside;
+        }
+    }
+
+    if inside {
+        (-best.0, (-best.1.0, -best.1.1))
+    } else {
+        best
+    }
+}
+
+/// Distance from `p` to the segment from `a` to `b`, along with the direction from the nearest
+/// point on the segment to `p`.
+pub fn segment_distance(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> (f64, (f64, f64)) {
+    let ab = (b.0 - a.0, b.1 - a.1);
+    let len_sq = ab.0 * ab.0 + ab.1 * ab.1;
+    let t = if len_sq > 0. {
+        (((p.0 - a.0) * ab.0 + (p.1 - a.1) * ab.1) / len_sq).clamp(0., 1.)
+    } else {
+        0.
+    };
+
+    let closest = (a.0 + t * ab.0, a.1 + t * ab.1);
+    let disp = (p.0 - closest.0, p.1 - closest.1);
+    let dist = disp.0.hypot(disp.1);
+    if dist > 0. {
+        (dist, (disp.0 / dist, disp.1 / dist))
+    } else {
+        // right on the segment, so pick one of its sides
+        let len = len_sq.sqrt().max(f64::EPSILON);
+        (0., (ab.1 / len, -ab.0 / len))
+    }
+}
+
+// signed area, positive when the vertices go clockwise on screen (y down)
+fn polygon_area(vertices: &[(f64, f64)]) -> f64 {
+    edges(vertices)
+        .map(|(a, b)| a.0 * b.1 - b.0 * a.1)
+        .sum::<f64>()
+        / 2.
+}
+
+fn edges(vertices: &[(f64, f64)]) -> impl Iterator<Item = ((f64, f64), (f64, f64))> + '_ {
+    vertices
+        .iter()
+        .zip(vertices.iter().cycle().skip(1))
+        .map(|(a, b)| (*a, *b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rect_properties() {
+        let rect = Shape::rect(4., 2.);
+        assert_eq!(rect.area(), 8.);
+        assert!((rect.unit_inertia() - (16. + 4.) / 12.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn signed_distance() {
+        let rect = Shape::rect(4., 2.);
+        assert_eq!(rect.distance((0., 3.)), (2., (0., 1.)));
+        assert_eq!(rect.distance((1.5, 0.)), (-0.5, (1., 0.)));
+
+        let circle = Shape::Circle { radius: 2. };
+        assert_eq!(circle.distance((0., -3.)), (1., (0., -1.)));
+    }
+
+    #[test]
+    fn polygon_is_centered() {
+        let triangle = Shape::polygon(vec![(0., 0.), (3., 0.), (0., 3.)]);
+        let Shape::Polygon { vertices } = triangle else {
+            unreachable!()
+        };
+        assert_eq!(vertices[0], (-1., -1.));
+    }
+}
@@ -0,0 +1,367 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use anyhow::{Context, bail};
+
+use crate::sim::{
+    MouseForce, body::Body, fish::Fish, obstacle::Mask, particle::Particle, shape::Shape,
+};
+
+const MAGIC: &[u8; 8] = b"FISHTANK";
+const VERSION: u32 = 5;
+
+/// The full state of a [`Simulation`], stored in a small binary format:
+///
+/// ```text
+/// magic "FISHTANK" | version: u32 | width: f64 | height: f64
+/// mouse force: u8 tag (0 = none, 1 = positive, 2 = negative, 3 = faucet, 4 = sponge),
+///              x: f64, y: f64
+/// particle count: u64 | particles: (x, y, vel_x, vel_y, accel_x, accel_y, last_dt) as f64s
+/// fish count: u64 | fish: (x, y, vel_x, vel_y, heading, size) as f64s
+/// body count: u64 | bodies: shape, then (x, y, vel_x, vel_y, angle, angular vel, mass) as f64s
+/// shape: u8 tag, then for a circle (0) its radius: f64, or for a polygon (1) a vertex count: u64
+///        and (x, y) f64s for each vertex
+/// wall row count: u64 | wall rows: cell count: u64, then a u8 per cell (1 = solid)
+/// ```
+///
+/// All numbers are little-endian. Snapshots from older versions, which lack the later sections
+/// and the particles' last step (before version 5), can still be read.
+pub struct Snapshot {
+    pub width: f64,
+    pub height: f64,
+    pub mouse_force: MouseForce,
+    pub particles: Vec<Particle>,
+    pub fish: Vec<Fish>,
+    pub bodies: Vec<Body>,
+    pub walls: Mask,
+}
+
+impl Snapshot {
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let file = File::create(path).with_context(|| format!("writing {}", path.display()))?;
+        let mut out = BufWriter::new(file);
+        self.write(&mut out)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path).with_context(|| format!("reading {}", path.display()))?;
+        Self::read(&mut BufReader::new(file)).with_context(|| format!("loading {}", path.display()))
+    }
+
+    pub fn write(&self, out: &mut impl Write) -> anyhow::Result<()> {
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        write_f64(out, self.width)?;
+        write_f64(out, self.height)?;
+
+        let (tag, x, y) = match self.mouse_force {
+            MouseForce::None => (0u8, 0., 0.),
+            MouseForce::Positive { x, y } => (1, x, y),
+            MouseForce::Negative { x, y } => (2, x, y),
+            MouseForce::Faucet { x, y } => (3, x, y),
+            MouseForce::Sponge { x, y } => (4, x, y),
+        };
+        out.write_all(&[tag])?;
+        write_f64(out, x)?;
+        write_f64(out, y)?;
+
+        out.write_all(&(self.particles.len() as u64).to_le_bytes())?;
+        for p in &self.particles {
+            let (accel_x, accel_y) = p.accel();
+            for v in [
+                p.x(),
+                p.y(),
+                p.vel_x(),
+                p.vel_y(),
+                accel_x,
+                accel_y,
+                p.last_dt(),
+            ] {
+                write_f64(out, v)?;
+            }
+        }
+
+        out.write_all(&(self.fish.len() as u64).to_le_bytes())?;
+        for f in &self.fish {
+            let (vel_x, vel_y) = f.vel();
+            for v in [f.x(), f.y(), vel_x, vel_y, f.heading(), f.size()] {
+                write_f64(out, v)?;
+            }
+        }
+
+        out.write_all(&(self.bodies.len() as u64).to_le_bytes())?;
+        for b in &self.bodies {
+            match b.shape() {
+                Shape::Circle { radius } => {
+                    out.write_all(&[0])?;
+                    write_f64(out, *radius)?;
+                }
+                Shape::Polygon { vertices } => {
+                    out.write_all(&[1])?;
+                    out.write_all(&(vertices.len() as u64).to_le_bytes())?;
+                    for (x, y) in vertices {
+                        write_f64(out, *x)?;
+                        write_f64(out, *y)?;
+                    }
+                }
+            }
+            let (vel_x, vel_y) = b.vel();
+            for v in [b.x(), b.y(), vel_x, vel_y, b.angle(), b.ang_vel(), b.mass()] {
+                write_f64(out, v)?;
+            }
+        }
+
+        let rows = self.walls.cells();
+        out.write_all(&(rows.len() as u64).to_le_bytes())?;
+        for row in rows {
+            out.write_all(&(row.len() as u64).to_le_bytes())?;
+            out.write_all(&row.iter().map(|solid| *solid as u8).collect::<Vec<_>>())?;
+        }
+        Ok(())
+    }
+
+    pub fn read(input: &mut impl Read) -> anyhow::Result<Self> {
+        let mut magic = [0u8; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            bail!("not a fishtank snapshot");
+        }
+
+        let mut version = [0u8; 4];
+        input.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version == 0 || version > VERSION {
+            bail!("unsupported snapshot version {}", version);
+        }
+
+        let width = read_f64(input)?;
+        let height = read_f64(input)?;
+
+        let mut tag = [0u8; 1];
+        input.read_exact(&mut tag)?;
+        let (x, y) = (read_f64(input)?, read_f64(input)?);
+        let mouse_force = match tag[0] {
+            0 => MouseForce::None,
+            1 => MouseForce::Positive { x, y },
+            2 => MouseForce::Negative { x, y },
+            3 => MouseForce::Faucet { x, y },
+            4 => MouseForce::Sponge { x, y },
+            tag => bail!("invalid mouse force tag {}", tag),
+        };
+
+        // counts come from the file, so the lists grow as records are read rather than being
+        // allocated up front, and a bogus count fails on the missing data
+        let mut particles = Vec::new();
+        for _ in 0..read_count(input)? {
+            let (x, y) = (read_f64(input)?, read_f64(input)?);
+            let (vel_x, vel_y) = (read_f64(input)?, read_f64(input)?);
+            let mut particle = Particle::new(x, y, vel_x, vel_y);
+            if version >= 5 {
+                let accel = (read_f64(input)?, read_f64(input)?);
+                particle = particle.with_last_step(accel, read_f64(input)?);
+            }
+            particles.push(particle);
+        }
+
+        let mut fish = Vec::new();
+        if version >= 2 {
+            let count = read_count(input)?;
+            for _ in 0..count {
+                let (x, y) = (read_f64(input)?, read_f64(input)?);
+                let vel = (read_f64(input)?, read_f64(input)?);
+                let (heading, size) = (read_f64(input)?, read_f64(input)?);
+                fish.push(Fish::new(x, y, heading, size).with_vel(vel));
+            }
+        }
+
+        let mut bodies = Vec::new();
+        if version >= 3 {
+            let count = read_count(input)?;
+            for _ in 0..count {
+                let shape = read_shape(input)?;
+                let pos = (read_f64(input)?, read_f64(input)?);
+                let vel = (read_f64(input)?, read_f64(input)?);
+                let (angle, ang_vel) = (read_f64(input)?, read_f64(input)?);
+                let mass = read_f64(input)?;
+                bodies.push(Body::from_parts(shape, pos, vel, angle, ang_vel, mass));
+            }
+        }
+
+        let mut cells = Vec::new();
+        if version >= 4 {
+            for _ in 0..read_count(input)? {
+                // only take the cells that are actually there
+                let len = read_count(input)?;
+                let mut row = Vec::new();
+                input.by_ref().take(len as u64).read_to_end(&mut row)?;
+                if row.len() != len {
+                    bail!("wall row cut short");
+                }
+                cells.push(row.into_iter().map(|cell| cell != 0).collect());
+            }
+        }
+
+        Ok(Self {
+            width,
+            height,
+            mouse_force,
+            particles,
+            fish,
+            bodies,
+            walls: Mask::from_cells((0., 0.), cells),
+        })
+    }
+}
+
+fn write_f64(out: &mut impl Write, value: f64) -> std::io::Result<()> {
+    out.write_all(&value.to_le_bytes())
+}
+
+fn read_shape(input: &mut impl Read) -> anyhow::Result<Shape> {
+    let mut tag = [0u8; 1];
+    input.read_exact(&mut tag)?;
+    match tag[0] {
+        0 => Ok(Shape::Circle {
+            radius: read_f64(input)?,
+        }),
+        1 => {
+            let mut vertices = Vec::new();
+            for _ in 0..read_count(input)? {
+                vertices.push((read_f64(input)?, read_f64(input)?));
+            }
+            Ok(Shape::Polygon { vertices })
+        }
+        tag => bail!("invalid shape tag {}", tag),
+    }
+}
+
+fn read_count(input: &mut impl Read) -> std::io::Result<usize> {
+    let mut bytes = [0u8; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes) as usize)
+}
+
+fn read_f64(input: &mut impl Read) -> std::io::Result<f64> {
+    let mut bytes = [0u8; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{
+        Simulation, body::BodyKind, integrator::Integrator, seed, settings::Settings,
+    };
+
+    #[test]
+    fn round_trip() {
+        let mut sim = Simulation::new(30., 20.);
+        sim.particles.push(Particle::new(1., 2., 3., -4.));
+        sim.particles
+            .push(Particle::new(5.5, 6.25, 0., 0.125).with_last_step((0.5, -9.75), 0.004));
+        sim.mouse_force.set_negative(10., 11.);
+        sim.fish.push(Fish::new(3., 4., 0.5, 1.5));
+        sim.drop_body(BodyKind::Boat, 15., 5.);
+        sim.drop_body(BodyKind::Ball, 5., 5.);
+        sim.paint_wall(3, 2, true);
+
+        let mut bytes = Vec::new();
+        sim.snapshot().write(&mut bytes).unwrap();
+        let restored = Simulation::from_snapshot(Snapshot::read(&mut bytes.as_slice()).unwrap(), 0);
+
+        assert_eq!((restored.width, restored.height), (30., 20.));
+        assert_eq!(
+            restored.mouse_force,
+            MouseForce::Negative { x: 10., y: 11. }
+        );
+        assert_eq!(restored.particles, sim.particles);
+        assert_eq!(restored.particles[1].accel(), (0.5, -9.75));
+        assert_eq!(restored.particles[1].last_dt(), 0.004);
+        assert_eq!(restored.fish, sim.fish);
+        assert_eq!(restored.bodies, sim.bodies);
+        assert_eq!(restored.walls, sim.walls);
+    }
+
+    #[test]
+    fn continue_exactly_after_loading() {
+        for integrator in [Integrator::Leapfrog, Integrator::VelocityVerlet] {
+            let mut settings = Settings::default();
+            settings.set_integrator(integrator);
+            let mut sim = Simulation::with_seed(30., 20., 0);
+            seed::seed(&mut sim, seed::SeedPattern::Dam, 100);
+            for _ in 0..10 {
+                sim.update(0.01, &settings);
+            }
+
+            let mut bytes = Vec::new();
+            sim.snapshot().write(&mut bytes).unwrap();
+            let mut restored =
+                Simulation::from_snapshot(Snapshot::read(&mut bytes.as_slice()).unwrap(), 0);
+            sim.update(0.01, &settings);
+            restored.update(0.01, &settings);
+            assert_eq!(restored.particles, sim.particles);
+        }
+    }
+
+    #[test]
+    fn read_version_1() {
+        let mut bytes = Vec::new();
+        let mut sim = Simulation::new(30., 20.);
+        sim.particles.push(Particle::new(1., 2., 3., -4.));
+        sim.snapshot().write(&mut bytes).unwrap();
+
+        // a version 1 snapshot is a later one without the particle's last step, nor the fish,
+        // body and wall counts on the end
+        bytes[8..12].copy_from_slice(&1u32.to_le_bytes());
+        bytes.drain(85..109);
+        bytes.truncate(bytes.len() - 24);
+
+        let snapshot = Snapshot::read(&mut bytes.as_slice()).unwrap();
+        assert_eq!(snapshot.particles, sim.particles);
+        assert!(snapshot.fish.is_empty());
+    }
+
+    #[test]
+    fn reject_other_files() {
+        let err = Snapshot::read(&mut b"not a snapshot at all".as_slice())
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "not a fishtank snapshot");
+
+        let mut bytes = Vec::new();
+        Simulation::new(1., 1.)
+            .snapshot()
+            .write(&mut bytes)
+            .unwrap();
+        bytes.truncate(bytes.len() - 1);
+        assert!(Snapshot::read(&mut bytes.as_slice()).is_err());
+
+        // a huge particle count with no particles after it
+        let mut bytes = Vec::new();
+        Simulation::new(1., 1.)
+            .snapshot()
+            .write(&mut bytes)
+            .unwrap();
+        bytes.truncate(45);
+        bytes.extend_from_slice(&(1u64 << 40).to_le_bytes());
+        assert!(Snapshot::read(&mut bytes.as_slice()).is_err());
+
+        // a wall row claiming far more cells than follow it
+        let mut bytes = Vec::new();
+        Simulation::new(1., 1.)
+            .snapshot()
+            .write(&mut bytes)
+            .unwrap();
+        bytes.truncate(bytes.len() - 8);
+        bytes.extend_from_slice(&1u64.to_le_bytes());
+        bytes.extend_from_slice(&(1u64 << 40).to_le_bytes());
+        bytes.push(1);
+        assert!(Snapshot::read(&mut bytes.as_slice()).is_err());
+    }
+}
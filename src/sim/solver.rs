@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+
+use clap::ValueEnum;
+use rayon::prelude::*;
+
+use crate::sim::{
+    Grid, GridPoint, Simulation, average,
+    body::BodyForce,
+    boundary::Boundary,
+    constants::{PARTICLE_MASS, PBF_CORRECTION, PBF_ITERATIONS, PBF_RELAXATION},
+    kernels::{poly6, spiky_grad},
+    settings::Settings,
+};
+
+/// How the fluid is moved forward each step.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Solver {
+    /// explicit SPH: pressure from density, then forces from pressure, then move
+    #[default]
+    Sph,
+    /// position based fluids: move, then push the particles apart until none of them is
+    /// denser than the target (after Macklin & Müller 2013)
+    Pbf,
+}
+
+impl Solver {
+    /// name shown in the info panel
+    pub fn label(self) -> &'static str {
+        match self {
+            Solver::Sph => "SPH",
+            Solver::Pbf => "Position based",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Solver::Sph => Solver::Pbf,
+            Solver::Pbf => Solver::Sph,
+        }
+    }
+}
+
+/// Position based fluids: moves the particles by every force but pressure, then pushes them
+/// apart until none of them is denser than the target. Returns the forces on the bodies, which
+/// the fluid only pushes on by touching them.
+pub(super) fn pbf_step(
+    sim: &mut Simulation,
+    grid: &Grid,
+    keys: Vec<GridPoint>,
+    spatial_hash: HashMap<GridPoint, Vec<usize>>,
+    dt_secs: f64,
+    settings: &Settings,
+) -> Vec<BodyForce> {
+    // density is only needed to scale the other forces; the projection works out its own
+    let (densities, _) = sim.compute_densities(grid, &keys, &spatial_hash, false, settings);
+    sim.avg_density = average(&densities);
+
+    let (forces, xsph, body_forces) =
+        sim.compute_forces(grid, keys, spatial_hash, &densities, None, settings);
+
+    let start = sim
+        .particles
+        .iter()
+        .map(|pt| (pt.x(), pt.y()))
+        .collect::<Vec<_>>();
+    sim.apply_forces(dt_secs, densities, forces, xsph, settings);
+    project_density(sim, &start, grid, settings, dt_secs);
+    body_forces
+}
+
+// moves particles that have already been moved by every other force until none of them is
+// denser than the target density, then works out their velocities from how far they went since
+// `start`
+fn project_density(
+    sim: &mut Simulation,
+    start: &[(f64, f64)],
+    grid: &Grid,
+    settings: &Settings,
+    dt_secs: f64,
+) {
+    let smoothing_radius = settings.smoothing_radius();
+    let smoothing_radius_sq = settings.smoothing_radius_sq();
+    let target_density = settings.target_density();
+
+    // particles only move a little while they're being solved, so their neighbors are found once
+    let (keys, spatial_hash) = sim.build_hash(grid);
+    let neighbors = sim
+        .particles
+        .par_iter()
+        .enumerate()
+        .map(|(idx1, pt)| {
+            grid.neighbors(keys[idx1])
+                .flat_map(|key| spatial_hash.get(&key).into_iter().flatten())
+                .copied()
+                .filter(|idx2| {
+                    let pt2 = &sim.particles[*idx2];
+                    let disp = grid.disp((pt.x(), pt.y()), (pt2.x(), pt2.y()));
+                    disp.0.powi(2) + disp.1.powi(2) <= smoothing_radius_sq
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    // an artificial pressure that keeps particles from clumping at the surface, relative to the
+    // kernel at a fixed fraction of the smoothing radius
+    let reference = poly6(
+        (0.2 * smoothing_radius).powi(2),
+        smoothing_radius,
+        smoothing_radius_sq,
+    );
+
+    for _ in 0..PBF_ITERATIONS {
+        let positions = sim
+            .particles
+            .iter()
+            .map(|pt| (pt.x(), pt.y()))
+            .collect::<Vec<_>>();
+
+        // gradient of the kernel at `disp`, with respect to the first particle
+        let grad = |disp: (f64, f64)| {
+            let dist = disp.0.hypot(disp.1);
+            if dist <= 0. {
+                return (0., 0.);
+            }
+            let coeff = PARTICLE_MASS * spiky_grad(dist, smoothing_radius) / dist;
+            (coeff * disp.0, coeff * disp.1)
+        };
+
+        // how much each particle's density constraint needs to move it
+        let lambdas = positions
+            .par_iter()
+            .zip(&neighbors)
+            .map(|(pos, neighbors)| {
+                let mut density = 0.;
+                let mut grad_self = (0., 0.);
+                let mut grad_sq_sum = 0.;
+                for idx2 in neighbors {
+                    let disp = grid.disp(*pos, positions[*idx2]);
+                    let sq_dist = disp.0.powi(2) + disp.1.powi(2);
+                    density +=
+                        PARTICLE_MASS * poly6(sq_dist, smoothing_radius, smoothing_radius_sq);
+
+                    let grad = grad(disp);
+                    grad_self.0 += grad.0;
+                    grad_self.1 += grad.1;
+                    grad_sq_sum += (grad.0.powi(2) + grad.1.powi(2)) / target_density.powi(2);
+                }
+                grad_sq_sum += (grad_self.0.powi(2) + grad_self.1.powi(2)) / target_density.powi(2);
+
+                // only too dense is a problem, so the surface isn't pulled together
+                let constraint = (density / target_density - 1.).max(0.);
+                -constraint / (grad_sq_sum + PBF_RELAXATION)
+            })
+            .collect::<Vec<_>>();
+
+        let deltas = positions
+            .par_iter()
+            .enumerate()
+            .map(|(idx1, pos)| {
+                let mut delta = (0., 0.);
+                for idx2 in &neighbors[idx1] {
+                    let disp = grid.disp(*pos, positions[*idx2]);
+                    let sq_dist = disp.0.powi(2) + disp.1.powi(2);
+                    let correction = -PBF_CORRECTION
+                        * (poly6(sq_dist, smoothing_radius, smoothing_radius_sq) / reference)
+                            .powi(4);
+
+                    let grad = grad(disp);
+                    let coeff = (lambdas[idx1] + lambdas[*idx2] + correction) / target_density;
+                    delta.0 += coeff * grad.0;
+                    delta.1 += coeff * grad.1;
+                }
+                delta
+            })
+            .collect::<Vec<_>>();
+
+        for (pt, delta) in sim.particles.iter_mut().zip(deltas) {
+            pt.set_x(pt.x() + delta.0);
+            pt.set_y(pt.y() + delta.1);
+        }
+        clamp_to_tank(sim, settings);
+    }
+
+    // particle velocities point against their motion
+    for (pt, start) in sim.particles.iter_mut().zip(start) {
+        pt.vel = ((start.0 - pt.x()) / dt_secs, (start.1 - pt.y()) / dt_secs);
+    }
+}
+
+// keeps particles inside the edges they can't go through while they're being solved
+fn clamp_to_tank(sim: &mut Simulation, settings: &Settings) {
+    let boundaries = settings.boundaries();
+    let solid = |boundary| matches!(boundary, Boundary::Reflective | Boundary::Sticky);
+    let (left, right) = (solid(boundaries.left), solid(boundaries.right));
+    let (top, bottom) = (solid(boundaries.top), solid(boundaries.bottom));
+
+    for pt in &mut sim.particles {
+        if left && pt.x() < 0. {
+            pt.set_x(0.);
+        }
+        if right && pt.x() > sim.width {
+            pt.set_x(sim.width);
+        }
+        if top && pt.y() < 0. {
+            pt.set_y(0.);
+        }
+        if bottom && pt.y() > sim.height {
+            pt.set_y(sim.height);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{body::BodyKind, eos::Eos, seed};
+
+    #[test]
+    fn pbf_ignores_pressure_settings() {
+        let run = |settings: &mut Settings| {
+            settings.set_solver(Solver::Pbf);
+            let mut sim = Simulation::with_seed(30., 20., 0);
+            seed::seed(&mut sim, seed::SeedPattern::Dam, 200);
+            sim.drop_body(BodyKind::Ball, 5., 10.);
+            for _ in 0..20 {
+                sim.update(0.01, settings);
+            }
+            (sim.particles, sim.bodies)
+        };
+
+        let mut stiff = Settings::default();
+        stiff.set_by_key("stiffness", 5000.).unwrap();
+        stiff.set_eos(Eos::Tait);
+        assert_eq!(run(&mut Settings::default()), run(&mut stiff));
+    }
+
+    #[test]
+    fn pbf_keeps_density_down_with_big_steps() {
+        let mut settings = Settings::default();
+        settings.set_solver(Solver::Pbf);
+        let mut sim = Simulation::with_seed(30., 20., 0);
+        seed::seed(&mut sim, seed::SeedPattern::Dam, 400);
+
+        for _ in 0..100 {
+            sim.update(0.02, &settings);
+        }
+        assert!(sim.avg_density() < 1.5 * settings.target_density());
+        assert!(
+            sim.particles()
+                .iter()
+                .all(|pt| pt.x().is_finite() && pt.y().is_finite())
+        );
+    }
+}